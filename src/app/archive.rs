@@ -0,0 +1,53 @@
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::PathBuf;
+
+use super::get_app_data_dir_path;
+use crate::{Task, TodoResult};
+
+const ARCHIVE_FILE: &str = "archive.jsonl";
+
+fn get_archive_file_path() -> PathBuf {
+    let mut path = get_app_data_dir_path();
+    path.push(ARCHIVE_FILE);
+    path
+}
+
+/// Append given tasks to the archive, one JSON document per line
+pub fn append_to_archive(tasks: &[&Task]) -> TodoResult<()> {
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(get_archive_file_path())?;
+    let mut lines = String::new();
+    for task in tasks {
+        lines.push_str(&serde_json::to_string(task)?);
+        lines.push('\n');
+    }
+    // a single write keeps a failed archive from ending up half written
+    file.write_all(lines.as_bytes())?;
+    Ok(())
+}
+
+/// Read every archived task in the order they were archived
+pub fn read_archive() -> TodoResult<Vec<Task>> {
+    let path = get_archive_file_path();
+    if !path.exists() {
+        return Ok(vec![]);
+    }
+    let mut tasks = vec![];
+    for (number, line) in BufReader::new(File::open(path)?).lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let mut task: Task = serde_json::from_str(&line)
+            .map_err(|e| format!("{} line {}: {}", ARCHIVE_FILE, number + 1, e))?;
+        // tasks archived before tasks had a UUID
+        if task.uuid.is_empty() {
+            task.uuid = task.legacy_uuid();
+        }
+        tasks.push(task);
+    }
+    Ok(tasks)
+}
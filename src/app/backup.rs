@@ -0,0 +1,90 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fs::{self, create_dir_all};
+use std::path::{Path, PathBuf};
+
+use super::{get_app_data_dir_path, AppData};
+use crate::TodoResult;
+
+const BACKUP_DIRECTORY: &str = "backups";
+/// Version of the backup format written by this build
+const BACKUP_VERSION: u32 = 1;
+
+/// A backup as written, borrowing the data it saves
+#[derive(Serialize)]
+struct BackupFile<'a> {
+    version: u32,
+    created_at: String,
+    app_data: &'a AppData,
+}
+
+/// A backup as read
+#[derive(Deserialize)]
+struct Backup {
+    app_data: AppData,
+}
+
+pub fn get_backup_dir_path() -> PathBuf {
+    let mut path = get_app_data_dir_path();
+    path.push(BACKUP_DIRECTORY);
+    path
+}
+
+impl AppData {
+    /// Write a versioned backup to given path, or to the backup directory under
+    /// a timestamped name, and return where it went
+    pub fn backup(&self, path: Option<&Path>) -> TodoResult<PathBuf> {
+        let path = match path {
+            Some(path) => path.to_path_buf(),
+            None => {
+                let backup_directory = get_backup_dir_path();
+                create_dir_all(&backup_directory)?;
+                let stamp = chrono::Local::now().format("%Y%m%d-%H%M%S");
+                let mut path = backup_directory.join(format!("todo-{}.json", stamp));
+                // backups taken within the same second get a counter
+                let mut count = 1;
+                while path.exists() {
+                    count += 1;
+                    path = backup_directory.join(format!("todo-{}-{}.json", stamp, count));
+                }
+                path
+            }
+        };
+        let backup = BackupFile {
+            version: BACKUP_VERSION,
+            created_at: crate::timestamp(),
+            app_data: self,
+        };
+        fs::write(&path, serde_json::to_string_pretty(&backup)?)
+            .map_err(|e| format!("Failed to write backup {}: {}", path.display(), e))?;
+        Ok(path)
+    }
+
+    /// Read a backup, or a plain copy of the data file, and bring it up to date
+    pub fn read_backup(path: &Path) -> TodoResult<AppData> {
+        let contents = fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read backup {}: {}", path.display(), e))?;
+        let invalid = |e: serde_json::Error| format!("{} is not a valid backup: {}", path.display(), e);
+        let value: Value = serde_json::from_str(&contents).map_err(invalid)?;
+        let mut app_data = match value.get("version") {
+            Some(version) => {
+                // a newer layout may not even parse, so check before reading it
+                if version.as_u64().is_none_or(|version| version > BACKUP_VERSION as u64) {
+                    return Err(format!(
+                        "Backup version {} is not supported, this version reads up to {}",
+                        version, BACKUP_VERSION
+                    )
+                    .into());
+                }
+                serde_json::from_value::<Backup>(value).map_err(invalid)?.app_data
+            }
+            // copies of the data file were the only backups before versions
+            None => serde_json::from_value(value).map_err(invalid)?,
+        };
+        app_data
+            .check_config()
+            .map_err(|e| format!("{} is not a valid backup: {}", path.display(), e))?;
+        app_data.migrate();
+        Ok(app_data)
+    }
+}
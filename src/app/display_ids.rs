@@ -0,0 +1,30 @@
+use std::fs;
+use std::path::PathBuf;
+
+use super::get_app_data_dir_path;
+use crate::{Task, TodoResult};
+
+const DISPLAY_IDS_FILE: &str = "display_ids.json";
+
+fn get_display_ids_file_path() -> PathBuf {
+    let mut path = get_app_data_dir_path();
+    path.push(DISPLAY_IDS_FILE);
+    path
+}
+
+/// Remember the UUIDs of listed tasks in the order shown, display id `@N`
+/// refers to the Nth of them until the next listing with display ids
+pub fn save_display_ids(tasks: &[&Task]) -> TodoResult<()> {
+    let uuids = tasks.iter().map(|task| &task.uuid).collect::<Vec<_>>();
+    fs::write(get_display_ids_file_path(), serde_json::to_string(&uuids)?)?;
+    Ok(())
+}
+
+/// UUIDs of the tasks of the last listing shown with display ids
+pub fn read_display_ids() -> TodoResult<Vec<String>> {
+    let path = get_display_ids_file_path();
+    if !path.exists() {
+        return Err("No listing with display ids yet, run `todo list --display-ids` first".into());
+    }
+    Ok(serde_json::from_str(&fs::read_to_string(path)?)?)
+}
@@ -7,21 +7,46 @@ use std::path::PathBuf;
 
 use crate::{Task, TodoResult};
 
+mod archive;
+mod backup;
+mod display_ids;
+mod workflow;
+
+pub use archive::*;
+pub use backup::*;
+pub use display_ids::*;
+pub use workflow::*;
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct AppData {
     pub tasks: BTreeMap<usize, Task>,
     pub tags: HashMap<String, Vec<usize>>,
     pub next_id: usize,
     pub config: AppConfig,
+    #[serde(default)]
+    pub tag_info: HashMap<String, TagInfo>,
+}
+
+/// User provided details of a tag, keyed by the lowercase tag name
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+pub struct TagInfo {
+    pub description: Option<String>,
+    pub color: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct AppConfig {
     pub date_format: String,
+    #[serde(default)]
+    pub workflow: Workflow,
+    /// Archive done tasks completed longer ago than this, e.g. `30d`
+    #[serde(default)]
+    pub archive_after: Option<String>,
 }
 
 const DEFAULT_APP_DATA_FILE: &str = "todo.json";
 pub const DEFAULT_DATE_FORMAT: &str = "%d-%m-%Y";
+pub const DEFAULT_TIMESTAMP_FORMAT: &str = "%d-%m-%Y %H:%M:%S";
 const DEFAULT_APP_DATA_DIRECTORY: &str = ".msa_todo_rust";
 
 fn get_app_data_dir_path() -> PathBuf {
@@ -51,18 +76,56 @@ impl AppData {
                 next_id: 1,
                 config: AppConfig {
                     date_format: DEFAULT_DATE_FORMAT.to_string(),
+                    workflow: Workflow::default(),
+                    archive_after: None,
                 },
+                tag_info: HashMap::new(),
             };
             let json = serde_json::to_string(&app_data)?;
             file.write_all(json.as_bytes())?;
             Ok(app_data)
         } else {
             let file = File::open(app_data_file)?;
-            let app_data: AppData = serde_json::from_reader(file)?;
+            let mut app_data: AppData = serde_json::from_reader(file)?;
+            app_data.migrate();
             Ok(app_data)
         }
     }
 
+    /// Check the parts of the config a user may have edited that nothing
+    /// could repair
+    pub fn check_config(&self) -> TodoResult<()> {
+        self.config
+            .workflow
+            .validate()
+            .map_err(|e| format!("Invalid workflow in config: {}", e).into())
+    }
+
+    /// Fill in fields missing from data written by older versions
+    fn migrate(&mut self) {
+        let workflow = &self.config.workflow;
+        for task in self.tasks.values_mut() {
+            if task.state.is_empty() {
+                task.state = match task.done {
+                    true => workflow.done.clone(),
+                    false => workflow.initial.clone(),
+                };
+            }
+            if task.uuid.is_empty() {
+                task.uuid = task.legacy_uuid();
+            }
+            // completion used to be recorded only in the state history
+            if task.done && task.completed_at.is_none() {
+                task.completed_at = task
+                    .history
+                    .iter()
+                    .rev()
+                    .find(|change| change.to == workflow.done)
+                    .map(|change| change.at.clone());
+            }
+        }
+    }
+
     pub fn save(&self) -> TodoResult<()> {
         let app_data_file = get_app_data_file_path();
         let mut file = File::create(app_data_file)?;
@@ -0,0 +1,115 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::TodoResult;
+
+/// States a task moves through and the moves allowed between them
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Workflow {
+    /// All states in the order they are usually passed
+    pub states: Vec<String>,
+    /// State of new and reopened tasks
+    pub initial: String,
+    /// State `todo start` moves to
+    pub started: String,
+    /// State of completed tasks
+    pub done: String,
+    /// State `todo cancel` moves to
+    pub cancelled: String,
+    /// States each state may move to, states without an entry are final
+    pub transitions: BTreeMap<String, Vec<String>>,
+}
+
+impl Default for Workflow {
+    fn default() -> Self {
+        let transitions = [
+            ("todo", vec!["in-progress", "done", "cancelled"]),
+            ("in-progress", vec!["todo", "review", "done", "cancelled"]),
+            ("review", vec!["in-progress", "done", "cancelled"]),
+            ("done", vec!["todo"]),
+            ("cancelled", vec!["todo"]),
+        ];
+        Workflow {
+            states: transitions
+                .iter()
+                .map(|(state, _)| state.to_string())
+                .collect(),
+            initial: "todo".to_string(),
+            started: "in-progress".to_string(),
+            done: "done".to_string(),
+            cancelled: "cancelled".to_string(),
+            transitions: transitions
+                .into_iter()
+                .map(|(state, targets)| {
+                    (
+                        state.to_string(),
+                        targets.into_iter().map(String::from).collect(),
+                    )
+                })
+                .collect(),
+        }
+    }
+}
+
+impl Workflow {
+    /// Check that the named states and every transition refer to known states
+    pub fn validate(&self) -> TodoResult<()> {
+        let known = |state: &str| self.states.iter().any(|s| s == state);
+        for (i, state) in self.states.iter().enumerate() {
+            if self.states[..i].contains(state) {
+                return Err(format!("state {} is listed more than once", state).into());
+            }
+        }
+        let named = [
+            ("initial", &self.initial),
+            ("started", &self.started),
+            ("done", &self.done),
+            ("cancelled", &self.cancelled),
+        ];
+        for (role, state) in named {
+            if !known(state) {
+                return Err(format!("{} state {} is not one of the states", role, state).into());
+            }
+        }
+        if self.done == self.cancelled {
+            return Err(format!("done and cancelled are both state {}", self.done).into());
+        }
+        for (from, targets) in &self.transitions {
+            for state in std::iter::once(from).chain(targets) {
+                if !known(state) {
+                    return Err(format!("transition from {} refers to unknown state {}", from, state).into());
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Tasks that are neither done nor cancelled
+    pub fn is_open(&self, state: &str) -> bool {
+        state != self.done && state != self.cancelled
+    }
+
+    pub fn check_state(&self, state: &str) -> TodoResult<()> {
+        match self.states.iter().any(|s| s == state) {
+            true => Ok(()),
+            false => Err(format!(
+                "Unknown state {}, expected one of {}",
+                state,
+                self.states.join(", ")
+            )
+            .into()),
+        }
+    }
+
+    pub fn check_transition(&self, from: &str, to: &str) -> TodoResult<()> {
+        self.check_state(to)?;
+        let allowed = self
+            .transitions
+            .get(from)
+            .is_some_and(|targets| targets.iter().any(|target| target == to));
+        match allowed {
+            true => Ok(()),
+            false => Err(format!("Can't move a task from {} to {}", from, to).into()),
+        }
+    }
+}
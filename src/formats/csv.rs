@@ -0,0 +1,225 @@
+use chrono::{NaiveDate, NaiveDateTime};
+use csv::{ReaderBuilder, StringRecord, Writer};
+
+use super::{format_annotations, parse_annotations, ImportOptions, ImportedTask, ParsedTasks};
+use crate::{parse_timestamp, Priority, Task, TodoResult};
+
+/// Task fields a column can be mapped to, the `id` column of an export is
+/// left out as imported tasks get new ids
+const FIELDS: [&str; 11] = [
+    "name",
+    "priority",
+    "due",
+    "tags",
+    "done",
+    "notes",
+    "uuid",
+    "state",
+    "created",
+    "completed",
+    "annotations",
+];
+
+/// Parse rows of a CSV file with a header, columns are mapped to fields by
+/// `options.map` (e.g. `Task=name`) or else by a header named like the field.
+/// Rows that can't be read are reported with their line number.
+pub fn parse(input: &str, options: &ImportOptions) -> TodoResult<ParsedTasks> {
+    let mut reader = ReaderBuilder::new().flexible(true).from_reader(input.as_bytes());
+    let headers = reader.headers()?.clone();
+    let columns = map_columns(&headers, &options.map)?;
+    if columns[0].is_none() {
+        return Err("No column maps to name, use --map to pick one (e.g. --map 'Task=name')".into());
+    }
+    let mut parsed = ParsedTasks::default();
+    for record in reader.records() {
+        let record = record?;
+        let line = record.position().map_or(0, |position| position.line() as usize);
+        let cell = |field: usize| {
+            columns[field]
+                .and_then(|column| record.get(column))
+                .map(str::trim)
+                .filter(|cell| !cell.is_empty())
+        };
+        match parse_row(cell, &options.date_format) {
+            Ok(mut task) => {
+                task.line = Some(line);
+                parsed.tasks.push(task);
+            }
+            Err(e) => parsed.errors.push(format!("line {}: {}", line, e)),
+        }
+    }
+    Ok(parsed)
+}
+
+/// Index of the column for each of `FIELDS`, if any
+fn map_columns(headers: &StringRecord, map: &[String]) -> TodoResult<Vec<Option<usize>>> {
+    let find = |header: &str| headers.iter().position(|h| h.trim().eq_ignore_ascii_case(header.trim()));
+    let mut columns = FIELDS.iter().map(|field| find(field)).collect::<Vec<_>>();
+    for mapping in map {
+        let (header, field) = mapping
+            .split_once('=')
+            .ok_or_else(|| format!("Invalid mapping {}, expected HEADER=FIELD", mapping))?;
+        let field = FIELDS
+            .iter()
+            .position(|f| f.eq_ignore_ascii_case(field.trim()))
+            .ok_or_else(|| format!("Unknown field {}, expected one of {}", field, FIELDS.join(", ")))?;
+        columns[field] = Some(find(header).ok_or_else(|| format!("Column {} not found", header))?);
+    }
+    Ok(columns)
+}
+
+fn parse_row<'a>(cell: impl Fn(usize) -> Option<&'a str>, date_format: &str) -> TodoResult<ImportedTask> {
+    let name = cell(0).ok_or("name is empty")?;
+    let due_date = match cell(2) {
+        Some(due) => Some(
+            NaiveDate::parse_from_str(due, date_format)
+                .map_err(|_| format!("invalid due date {}, expected format: {}", due, date_format))?,
+        ),
+        None => None,
+    };
+    let done = match cell(4).map(|done| done.to_lowercase()) {
+        None => false,
+        Some(done) => match done.as_str() {
+            "true" | "yes" | "y" | "x" | "1" | "done" => true,
+            "false" | "no" | "n" | "0" => false,
+            _ => return Err(format!("invalid done value {}, expected true or false", done).into()),
+        },
+    };
+    Ok(ImportedTask {
+        name: name.to_string(),
+        priority: cell(1).map(str::parse::<Priority>).transpose()?.unwrap_or_default(),
+        due_date,
+        tags: cell(3)
+            .map(|tags| tags.split(',').map(|tag| tag.trim().to_string()).collect())
+            .unwrap_or_default(),
+        done,
+        notes: cell(5).map(String::from),
+        uuid: cell(6).map(String::from),
+        state: cell(7).map(String::from),
+        created_at: cell(8).map(parse_date_time).transpose()?,
+        completed_at: cell(9).map(parse_date_time).transpose()?,
+        annotations: cell(10).map(parse_annotations).unwrap_or_default(),
+        ..Default::default()
+    })
+}
+
+fn parse_date_time(date: &str) -> TodoResult<NaiveDateTime> {
+    parse_timestamp(date).ok_or_else(|| format!("invalid timestamp {}, expected format: DD-MM-YYYY HH:MM:SS", date).into())
+}
+
+/// Write a header and one row per task, with due dates in given format
+pub fn format(tasks: &[&Task], date_format: &str) -> TodoResult<String> {
+    let mut writer = Writer::from_writer(vec![]);
+    writer.write_record([
+        "id",
+        "uuid",
+        "name",
+        "priority",
+        "due",
+        "tags",
+        "done",
+        "state",
+        "created",
+        "completed",
+        "notes",
+        "annotations",
+    ])?;
+    for task in tasks {
+        writer.write_record([
+            task.id.to_string(),
+            task.uuid.clone(),
+            task.name.clone(),
+            task.priority.to_string(),
+            task.get_parsed_due_date()
+                .map(|due| due.format(date_format).to_string())
+                .unwrap_or_default(),
+            task.tags.clone().unwrap_or_default().join(","),
+            task.done.to_string(),
+            task.state.clone(),
+            task.created_at.clone(),
+            task.completed_at.clone().unwrap_or_default(),
+            task.notes.clone().unwrap_or_default(),
+            format_annotations(&task.annotations),
+        ])?;
+    }
+    Ok(String::from_utf8(writer.into_inner()?)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Annotation, Workflow, DEFAULT_DATE_FORMAT, DEFAULT_TIMESTAMP_FORMAT};
+
+    fn options(map: &[&str]) -> ImportOptions {
+        ImportOptions {
+            map: map.iter().map(|mapping| mapping.to_string()).collect(),
+            date_format: DEFAULT_DATE_FORMAT.to_string(),
+        }
+    }
+
+    #[test]
+    fn round_trips_tasks() {
+        let workflow = Workflow::default();
+        let mut task = Task::new(
+            7,
+            "Plan trip, \"soon\"".to_string(),
+            Priority::Critical,
+            Some("06-01-2024".to_string()),
+            Some(vec!["travel".to_string(), "home/family".to_string()]),
+            DEFAULT_DATE_FORMAT,
+            &workflow,
+        )
+        .unwrap();
+        task.notes = Some("book flights\nrent a car".to_string());
+        task.annotations = vec![
+            Annotation {
+                at: "03-01-2024 10:00:00".to_string(),
+                text: "asked Sam".to_string(),
+            },
+            Annotation {
+                at: "04-01-2024 11:30:00".to_string(),
+                text: "Sam said yes".to_string(),
+            },
+        ];
+        task.move_to(&workflow.done, &workflow).unwrap();
+
+        let parsed = parse(&format(&[&task], DEFAULT_DATE_FORMAT).unwrap(), &options(&[])).unwrap();
+        assert!(parsed.errors.is_empty(), "{:?}", parsed.errors);
+        assert_eq!(parsed.tasks.len(), 1);
+        let imported = &parsed.tasks[0];
+        let timestamp = |at: Option<NaiveDateTime>| at.map(|at| at.format(DEFAULT_TIMESTAMP_FORMAT).to_string());
+        assert_eq!(imported.name, task.name);
+        assert_eq!(imported.uuid.as_deref(), Some(task.uuid.as_str()));
+        assert_eq!(imported.priority, Priority::Critical);
+        assert_eq!(imported.due_date, task.get_parsed_due_date());
+        assert_eq!(imported.tags, vec!["travel", "home/family"]);
+        assert!(imported.done);
+        assert_eq!(imported.state.as_deref(), Some("done"));
+        assert_eq!(imported.notes, task.notes);
+        assert_eq!(timestamp(imported.created_at).as_ref(), Some(&task.created_at));
+        assert_eq!(timestamp(imported.completed_at), task.completed_at);
+        let annotations = imported
+            .annotations
+            .iter()
+            .map(|annotation| (annotation.at.as_str(), annotation.text.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            annotations,
+            vec![("03-01-2024 10:00:00", "asked Sam"), ("04-01-2024 11:30:00", "Sam said yes")]
+        );
+    }
+
+    #[test]
+    fn maps_columns_and_reports_bad_rows() {
+        let input = "Task,Due Date,Finished\nBuy milk,06-01-2024,yes\n,,\nPay rent,someday,no\n";
+        let parsed = parse(input, &options(&["Task=name", "Due Date=due", "Finished=done"])).unwrap();
+        assert_eq!(parsed.tasks.len(), 1);
+        assert_eq!(parsed.tasks[0].name, "Buy milk");
+        assert!(parsed.tasks[0].done);
+        assert_eq!(parsed.tasks[0].line, Some(2));
+        assert_eq!(parsed.errors.len(), 2);
+        assert!(parsed.errors[0].starts_with("line 3:"));
+        assert!(parse(input, &options(&[])).is_err());
+        assert!(parse(input, &options(&["Task=title"])).is_err());
+    }
+}
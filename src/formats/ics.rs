@@ -0,0 +1,341 @@
+use chrono::{NaiveDate, NaiveDateTime};
+
+use super::{
+    annotation_timestamp, format_annotations, format_utc, format_utc_timestamp, parse_annotations, parse_utc, ImportedTask,
+    ParsedTasks,
+};
+use crate::app::Workflow;
+use crate::{Annotation, Priority, Task, TodoResult};
+
+const DATE_FORMAT: &str = "%Y%m%d";
+const LOCAL_FORMAT: &str = "%Y%m%dT%H%M%S";
+
+/// Write an RFC 5545 calendar with a VTODO per task
+pub fn format(tasks: &[&Task], workflow: &Workflow) -> String {
+    let now = format_utc(chrono::Local::now().naive_local()).unwrap_or_default();
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        "PRODID:-//todo-rust//todo//EN".to_string(),
+    ];
+    for task in tasks {
+        lines.push("BEGIN:VTODO".to_string());
+        lines.push(format!("UID:{}", task.uuid));
+        lines.push(format!("DTSTAMP:{}", now));
+        lines.push(format!("SUMMARY:{}", escape(&task.name)));
+        if let Some(due) = task.get_parsed_due_date() {
+            lines.push(format!("DUE;VALUE=DATE:{}", due.format(DATE_FORMAT)));
+        }
+        // 1 to 4 are high, 5 medium and 6 to 9 low, 0 leaves it undefined
+        let priority = match task.priority {
+            Priority::None => 0,
+            Priority::Low => 7,
+            Priority::Medium => 5,
+            Priority::High => 3,
+            Priority::Critical => 1,
+        };
+        if priority > 0 {
+            lines.push(format!("PRIORITY:{}", priority));
+        }
+        if let Some(tags) = &task.tags {
+            let tags = tags.iter().map(|tag| escape(tag)).collect::<Vec<_>>();
+            lines.push(format!("CATEGORIES:{}", tags.join(",")));
+        }
+        let status = if task.done {
+            "COMPLETED"
+        } else if task.state == workflow.cancelled {
+            "CANCELLED"
+        } else if task.state == workflow.initial {
+            "NEEDS-ACTION"
+        } else {
+            "IN-PROCESS"
+        };
+        lines.push(format!("STATUS:{}", status));
+        // annotations follow the notes after a blank line
+        let description = [task.notes.clone().unwrap_or_default(), format_annotations(&task.annotations)]
+            .into_iter()
+            .filter(|part| !part.is_empty())
+            .collect::<Vec<_>>()
+            .join("\n\n");
+        if !description.is_empty() {
+            lines.push(format!("DESCRIPTION:{}", escape(&description)));
+        }
+        let dates = [
+            ("CREATED", Some(&task.created_at)),
+            ("LAST-MODIFIED", task.updated_at.as_ref()),
+            ("COMPLETED", task.completed_at.as_ref()),
+        ];
+        for (name, date) in dates {
+            if let Some(date) = date.and_then(|date| format_utc_timestamp(date)) {
+                lines.push(format!("{}:{}", name, date));
+            }
+        }
+        lines.push("END:VTODO".to_string());
+    }
+    lines.push("END:VCALENDAR".to_string());
+    lines.iter().map(|line| fold(line)).collect()
+}
+
+/// Read the VTODO components of a calendar, other components are skipped
+pub fn parse(input: &str) -> TodoResult<ParsedTasks> {
+    let mut parsed = ParsedTasks::default();
+    // a todo with the line it starts on, or the error that made it unreadable
+    let mut current: Option<(usize, Result<ImportedTask, String>)> = None;
+    // components nested in a todo, such as alarms, have properties of their own
+    let mut nested = 0;
+    for (number, line) in unfold(input) {
+        let Some((name, value)) = line.split_once(':') else {
+            continue;
+        };
+        // parameters such as `;VALUE=DATE` follow the property name, dates tell their type by themselves
+        let name = name.split(';').next().unwrap_or_default();
+        match (name.to_uppercase().as_str(), &mut current) {
+            ("BEGIN", Some(_)) => nested += 1,
+            ("END", Some(_)) if nested > 0 => nested -= 1,
+            (_, Some(_)) if nested > 0 => {}
+            ("BEGIN", None) if value.eq_ignore_ascii_case("VTODO") => {
+                current = Some((number, Ok(ImportedTask::default())))
+            }
+            ("END", Some(_)) if value.eq_ignore_ascii_case("VTODO") => {
+                let (line, task) = current.take().unwrap();
+                match task.and_then(|task| match task.name.is_empty() {
+                    true => Err("todo has no summary".to_string()),
+                    false => Ok(task),
+                }) {
+                    Ok(task) => parsed.tasks.push(ImportedTask {
+                        line: Some(line),
+                        ..task
+                    }),
+                    Err(e) => parsed.errors.push(format!("line {}: {}", line, e)),
+                }
+            }
+            (name, Some((_, Ok(task)))) => {
+                if let Err(e) = apply_property(task, name, value) {
+                    current.as_mut().unwrap().1 = Err(e);
+                }
+            }
+            _ => {}
+        }
+    }
+    if let Some((line, _)) = current {
+        return Err(format!("line {}: todo is missing END:VTODO", line).into());
+    }
+    Ok(parsed)
+}
+
+fn apply_property(task: &mut ImportedTask, name: &str, value: &str) -> Result<(), String> {
+    match name {
+        "UID" => task.uuid = Some(unescape(value)),
+        "SUMMARY" => task.name = unescape(value),
+        "DESCRIPTION" => {
+            let (notes, annotations) = split_description(&unescape(value));
+            task.notes = Some(notes).filter(|notes| !notes.is_empty());
+            task.annotations = annotations;
+        }
+        "DUE" => task.due_date = Some(parse_date(value)?.date()),
+        "PRIORITY" => {
+            task.priority = match value.trim().parse::<u8>() {
+                Ok(0) => Priority::None,
+                Ok(1..=2) => Priority::Critical,
+                Ok(3..=4) => Priority::High,
+                Ok(5) => Priority::Medium,
+                Ok(6..=9) => Priority::Low,
+                _ => return Err(format!("invalid priority {}", value)),
+            }
+        }
+        "CATEGORIES" => task.tags.extend(
+            split_unescaped(value)
+                .iter()
+                .map(|tag| unescape(tag))
+                .filter(|tag| !tag.trim().is_empty()),
+        ),
+        "STATUS" => {
+            task.done = value.eq_ignore_ascii_case("COMPLETED");
+            task.cancelled = value.eq_ignore_ascii_case("CANCELLED");
+            task.started = value.eq_ignore_ascii_case("IN-PROCESS");
+        }
+        "CREATED" => task.created_at = Some(parse_date(value)?),
+        "LAST-MODIFIED" => task.updated_at = Some(parse_date(value)?),
+        "COMPLETED" => task.completed_at = Some(parse_date(value)?),
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Split a description into the notes and the annotations `format` writes
+/// after them, which start on the first timestamped line after a blank one
+fn split_description(description: &str) -> (String, Vec<Annotation>) {
+    let lines = description.lines().collect::<Vec<_>>();
+    let start = (0..lines.len())
+        .find(|&i| (i == 0 || lines[i - 1].is_empty()) && annotation_timestamp(lines[i]).is_some());
+    match start {
+        Some(start) => (
+            lines[..start].join("\n").trim_end().to_string(),
+            parse_annotations(&lines[start..].join("\n")),
+        ),
+        None => (description.to_string(), vec![]),
+    }
+}
+
+/// Read a date, a UTC date-time or a floating date-time as local time
+fn parse_date(value: &str) -> Result<NaiveDateTime, String> {
+    let value = value.trim();
+    parse_utc(value)
+        .or_else(|| NaiveDateTime::parse_from_str(value, LOCAL_FORMAT).ok())
+        .or_else(|| NaiveDate::parse_from_str(value, DATE_FORMAT).ok()?.and_hms_opt(0, 0, 0))
+        .ok_or_else(|| format!("invalid date {}", value))
+}
+
+/// Escape text values as RFC 5545 section 3.3.11 requires
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+}
+
+fn unescape(text: &str) -> String {
+    let mut unescaped = String::new();
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') | Some('N') => unescaped.push('\n'),
+            Some(next) => unescaped.push(next),
+            None => {}
+        }
+    }
+    unescaped
+}
+
+/// Split a list value on the commas that aren't escaped
+fn split_unescaped(value: &str) -> Vec<String> {
+    let mut parts = vec![String::new()];
+    let mut escaped = false;
+    for c in value.chars() {
+        match c {
+            ',' if !escaped => parts.push(String::new()),
+            _ => parts.last_mut().unwrap().push(c),
+        }
+        escaped = c == '\\' && !escaped;
+    }
+    parts
+}
+
+/// Break a line into parts of at most 75 bytes, continuation lines start with
+/// a space, and end each with CRLF
+fn fold(line: &str) -> String {
+    let mut folded = String::new();
+    let mut length = 0;
+    for c in line.chars() {
+        if length + c.len_utf8() > 75 {
+            folded.push_str("\r\n ");
+            length = 1;
+        }
+        folded.push(c);
+        length += c.len_utf8();
+    }
+    folded.push_str("\r\n");
+    folded
+}
+
+/// Join folded lines back together, keeping the number of the line each
+/// starts on
+fn unfold(input: &str) -> Vec<(usize, String)> {
+    let mut lines: Vec<(usize, String)> = vec![];
+    for (number, line) in input.lines().enumerate() {
+        let line = line.trim_end_matches('\r');
+        match (line.strip_prefix([' ', '\t']), lines.last_mut()) {
+            (Some(continuation), Some((_, last))) => last.push_str(continuation),
+            _ => lines.push((number + 1, line.to_string())),
+        }
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{DEFAULT_DATE_FORMAT, DEFAULT_TIMESTAMP_FORMAT};
+
+    fn new_task(name: &str, workflow: &Workflow) -> Task {
+        Task::new(1, name.to_string(), Priority::None, None, None, DEFAULT_DATE_FORMAT, workflow).unwrap()
+    }
+
+    fn timestamp(at: Option<NaiveDateTime>) -> Option<String> {
+        at.map(|at| at.format(DEFAULT_TIMESTAMP_FORMAT).to_string())
+    }
+
+    #[test]
+    fn round_trips_tasks() {
+        let workflow = Workflow::default();
+        // long enough to be folded, with multi-byte characters across the fold
+        let name = "Décider; réserver, payer \\ confirmer l'hôtel à Zürich pour la conférence d'été ✈✈✈✈✈";
+        let mut task = Task::new(
+            1,
+            name.to_string(),
+            Priority::High,
+            Some("06-01-2024".to_string()),
+            Some(vec!["travel,work".to_string(), "home/family".to_string()]),
+            DEFAULT_DATE_FORMAT,
+            &workflow,
+        )
+        .unwrap();
+        task.notes = Some("book flights\n\nrent a car".to_string());
+        task.annotations = vec![Annotation {
+            at: "03-01-2024 10:00:00".to_string(),
+            text: "asked Sam".to_string(),
+        }];
+        task.move_to(&workflow.started, &workflow).unwrap();
+        let mut cancelled = new_task("Skip it", &workflow);
+        cancelled.move_to(&workflow.cancelled, &workflow).unwrap();
+        let mut done = new_task("Finish it", &workflow);
+        done.move_to(&workflow.done, &workflow).unwrap();
+        let todo = new_task("Start it", &workflow);
+
+        let output = format(&[&task, &cancelled, &done, &todo], &workflow);
+        assert!(output.lines().all(|line| line.len() <= 75), "{}", output);
+        let parsed = parse(&output).unwrap();
+        assert!(parsed.errors.is_empty(), "{:?}", parsed.errors);
+        let [imported, imported_cancelled, imported_done, imported_todo] = &parsed.tasks[..] else {
+            panic!("expected 4 tasks, got {}", parsed.tasks.len());
+        };
+        assert_eq!(imported.name, name);
+        assert_eq!(imported.uuid.as_deref(), Some(task.uuid.as_str()));
+        assert_eq!(imported.priority, Priority::High);
+        assert_eq!(imported.due_date, task.get_parsed_due_date());
+        assert_eq!(imported.tags, vec!["travel,work", "home/family"]);
+        assert_eq!(imported.notes, task.notes);
+        assert_eq!(imported.annotations.len(), 1);
+        assert_eq!(imported.annotations[0].at, "03-01-2024 10:00:00");
+        assert_eq!(imported.annotations[0].text, "asked Sam");
+        assert_eq!(timestamp(imported.created_at).as_ref(), Some(&task.created_at));
+        assert_eq!(timestamp(imported.updated_at), task.updated_at);
+        assert!(imported.started && !imported.done && !imported.cancelled);
+
+        assert!(imported_cancelled.cancelled && !imported_cancelled.started);
+        assert!(imported_done.done && !imported_done.started);
+        assert_eq!(timestamp(imported_done.completed_at), done.completed_at);
+        assert!(!imported_todo.started && !imported_todo.done && !imported_todo.cancelled);
+        assert!(imported_todo.notes.is_none() && imported_todo.tags.is_empty());
+    }
+
+    #[test]
+    fn skips_nested_components_and_reports_bad_todos() {
+        let input = concat!(
+            "BEGIN:VCALENDAR\r\n",
+            "BEGIN:VTODO\r\nSUMMARY:Call\r\n  mom\r\nBEGIN:VALARM\r\nDESCRIPTION:ring\r\nEND:VALARM\r\nEND:VTODO\r\n",
+            "BEGIN:VTODO\r\nSUMMARY:Pay\r\nDUE:someday\r\nEND:VTODO\r\n",
+            "END:VCALENDAR\r\n",
+        );
+        let parsed = parse(input).unwrap();
+        assert_eq!(parsed.tasks.len(), 1);
+        assert_eq!(parsed.tasks[0].name, "Call mom");
+        assert!(parsed.tasks[0].notes.is_none());
+        assert_eq!(parsed.errors, vec!["line 9: invalid date someday"]);
+        assert!(parse("BEGIN:VTODO\r\nSUMMARY:Call\r\n").is_err());
+    }
+}
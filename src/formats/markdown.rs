@@ -0,0 +1,227 @@
+use chrono::NaiveDate;
+
+use super::{ExportOptions, ImportOptions, ImportedTask, ParsedTasks};
+use crate::{Task, Workflow};
+
+/// Write a GitHub-style checklist, e.g. `- [ ] Buy milk (due 28-01-2024) #groceries`,
+/// with a section per tag if asked for. Cancelled tasks are struck through.
+pub fn format(tasks: &[&Task], workflow: &Workflow, options: &ExportOptions) -> String {
+    if !options.group_by_tag {
+        return tasks.iter().map(|task| format_item(task, workflow, &options.date_format)).collect();
+    }
+    let mut tags = tasks
+        .iter()
+        .flat_map(|task| task.tags.iter().flatten())
+        .map(|tag| tag.to_lowercase())
+        .collect::<Vec<_>>();
+    tags.sort();
+    tags.dedup();
+    let mut sections = vec![];
+    for tag in tags {
+        // tasks are listed under each of their tags
+        let items = tasks
+            .iter()
+            .filter(|task| task.tags.iter().flatten().any(|t| t.to_lowercase() == tag))
+            .map(|task| format_item(task, workflow, &options.date_format))
+            .collect::<String>();
+        sections.push(format!("## {}\n\n{}", tag, items));
+    }
+    let untagged = tasks
+        .iter()
+        .filter(|task| task.tags.is_none())
+        .map(|task| format_item(task, workflow, &options.date_format))
+        .collect::<String>();
+    if !untagged.is_empty() {
+        sections.push(format!("## Untagged\n\n{}", untagged));
+    }
+    sections.join("\n")
+}
+
+fn format_item(task: &Task, workflow: &Workflow, date_format: &str) -> String {
+    let mut words = vec![match task.done {
+        true => "- [x]".to_string(),
+        false => "- [ ]".to_string(),
+    }];
+    let name = task.name.split_whitespace().map(escape_word).collect::<Vec<_>>().join(" ");
+    match task.state == workflow.cancelled {
+        true => words.push(format!("~~{}~~", name)),
+        false => words.push(name),
+    }
+    if let Some(due) = task.get_parsed_due_date() {
+        words.push(format!("(due {})", due.format(date_format)));
+    }
+    for tag in task.tags.iter().flatten() {
+        // tags end at whitespace
+        words.push(format!("#{}", tag.split_whitespace().collect::<Vec<_>>().join("_")));
+    }
+    format!("{}\n", words.join(" "))
+}
+
+/// Read the checklist items of a markdown document, including nested ones,
+/// everything else is skipped
+pub fn parse(input: &str, options: &ImportOptions) -> ParsedTasks {
+    let mut parsed = ParsedTasks::default();
+    for (number, line) in input.lines().enumerate() {
+        let Some((done, item)) = checklist_item(line) else {
+            continue;
+        };
+        match parse_item(item, &options.date_format) {
+            Ok(task) => parsed.tasks.push(ImportedTask {
+                line: Some(number + 1),
+                done,
+                ..task
+            }),
+            Err(e) => parsed.errors.push(format!("line {}: {}", number + 1, e)),
+        }
+    }
+    parsed
+}
+
+/// Whether the item is checked and its text, if the line is a checklist item
+fn checklist_item(line: &str) -> Option<(bool, &str)> {
+    let line = line.trim_start();
+    let line = ["- ", "* ", "+ "]
+        .iter()
+        .find_map(|bullet| line.strip_prefix(bullet))?
+        .trim_start();
+    let (done, item) = match line.get(..3)? {
+        "[ ]" => (false, &line[3..]),
+        "[x]" | "[X]" => (true, &line[3..]),
+        _ => return None,
+    };
+    Some((done, item))
+}
+
+fn parse_item(item: &str, date_format: &str) -> Result<ImportedTask, String> {
+    let mut task = ImportedTask::default();
+    let mut item = item.to_string();
+    // a `(due` escaped as part of the name is text
+    let due = item.match_indices("(due ").map(|(start, _)| start).find(|&start| !item[..start].ends_with('\\'));
+    if let Some(start) = due {
+        let end = start
+            + item[start..]
+                .find(')')
+                .ok_or("due date is missing its closing parenthesis")?;
+        let due = item[start + 5..end].trim();
+        task.due_date = Some(
+            NaiveDate::parse_from_str(due, date_format)
+                .map_err(|_| format!("invalid due date {}, expected format: {}", due, date_format))?,
+        );
+        item.replace_range(start..=end, "");
+    }
+    // only the `#tag` words ending the item are tags, others like `#123` are text
+    let mut words = item.split_whitespace().collect::<Vec<_>>();
+    while let Some(tag) = words.last().and_then(|word| word.strip_prefix('#')).filter(|tag| !tag.is_empty()) {
+        task.tags.insert(0, tag.to_string());
+        words.pop();
+    }
+    let mut name = words.join(" ");
+    if let Some(struck) = name.strip_prefix("~~").and_then(|name| name.strip_suffix("~~")) {
+        task.cancelled = true;
+        name = struck.to_string();
+    }
+    let name = name
+        .split_whitespace()
+        .map(|word| word.strip_prefix('\\').filter(|word| !word.is_empty()).unwrap_or(word))
+        .collect::<Vec<_>>();
+    if name.is_empty() {
+        return Err("item has no text".to_string());
+    }
+    task.name = name.join(" ");
+    Ok(task)
+}
+
+/// Backslash-escape a word of a task name that would be read as a tag, a due
+/// date or strikethrough, as markdown allows, so the name reads the same when
+/// rendered
+fn escape_word(word: &str) -> String {
+    let special = ["#", "\\", "~~", "(due"].iter().any(|prefix| word.starts_with(prefix));
+    match word.len() > 1 && special {
+        true => format!("\\{}", word),
+        false => word.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Priority, DEFAULT_DATE_FORMAT};
+
+    fn task(name: &str, due: Option<&str>, tags: &[&str], workflow: &Workflow) -> Task {
+        let tags = tags.iter().map(|tag| tag.to_string()).collect();
+        Task::new(1, name.to_string(), Priority::None, due.map(String::from), Some(tags), DEFAULT_DATE_FORMAT, workflow)
+            .unwrap()
+    }
+
+    fn round_trip(tasks: &[&Task], workflow: &Workflow) -> Vec<ImportedTask> {
+        let export = ExportOptions {
+            date_format: DEFAULT_DATE_FORMAT.to_string(),
+            group_by_tag: false,
+        };
+        let import = ImportOptions {
+            date_format: DEFAULT_DATE_FORMAT.to_string(),
+            ..Default::default()
+        };
+        let parsed = parse(&format(tasks, workflow, &export), &import);
+        assert!(parsed.errors.is_empty(), "{:?}", parsed.errors);
+        assert_eq!(parsed.tasks.len(), tasks.len());
+        parsed.tasks
+    }
+
+    #[test]
+    fn round_trips_tasks() {
+        let workflow = Workflow::default();
+        let open = task("Buy milk", Some("28-01-2024"), &["groceries", "home errands"], &workflow);
+        let mut done = task("Fix #123 and #456", None, &[], &workflow);
+        done.move_to(&workflow.done, &workflow).unwrap();
+        let mut cancelled = task("Old idea", None, &["someday"], &workflow);
+        cancelled.move_to(&workflow.cancelled, &workflow).unwrap();
+
+        let imported = round_trip(&[&open, &done, &cancelled], &workflow);
+        assert_eq!(imported[0].name, "Buy milk");
+        assert_eq!(imported[0].due_date, open.get_parsed_due_date());
+        assert_eq!(imported[0].tags, vec!["groceries", "home_errands"]);
+        assert!(!imported[0].done && !imported[0].cancelled);
+        assert_eq!(imported[1].name, "Fix #123 and #456");
+        assert!(imported[1].tags.is_empty());
+        assert!(imported[1].done && !imported[1].cancelled);
+        assert_eq!(imported[2].name, "Old idea");
+        assert_eq!(imported[2].tags, vec!["someday"]);
+        assert!(imported[2].cancelled && !imported[2].done);
+    }
+
+    #[test]
+    fn keeps_names_that_look_like_syntax() {
+        let workflow = Workflow::default();
+        for name in [
+            "Tag #work",
+            "~~not cancelled~~",
+            r"C:\temp \ cleanup",
+            "Pay bill (due soon)",
+            "# and ~ alone",
+        ] {
+            let imported = round_trip(&[&task(name, Some("28-01-2024"), &["x"], &workflow)], &workflow);
+            assert_eq!(imported[0].name, name);
+            assert_eq!(imported[0].tags, vec!["x"]);
+            assert_eq!(imported[0].due_date, NaiveDate::from_ymd_opt(2024, 1, 28));
+            assert!(!imported[0].cancelled);
+        }
+    }
+
+    #[test]
+    fn reads_checklist_items_and_reports_bad_ones() {
+        let options = ImportOptions {
+            date_format: DEFAULT_DATE_FORMAT.to_string(),
+            ..Default::default()
+        };
+        let input = "# Plan\n\n* [X] Book hall\n  - [ ] Send invites (due 31-02-2024)\n- plain item\n+ [ ] #only\n";
+        let parsed = parse(input, &options);
+        assert_eq!(parsed.tasks.len(), 1);
+        assert_eq!(parsed.tasks[0].name, "Book hall");
+        assert!(parsed.tasks[0].done);
+        assert_eq!(parsed.tasks[0].line, Some(3));
+        assert_eq!(parsed.errors.len(), 2);
+        assert!(parsed.errors[0].starts_with("line 4: invalid due date"));
+        assert_eq!(parsed.errors[1], "line 6: item has no text");
+    }
+}
@@ -0,0 +1,156 @@
+use chrono::{Local, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use clap::ValueEnum;
+
+use crate::{parse_timestamp, timestamp, Annotation, Priority, Task, TodoResult, Workflow};
+
+pub mod csv;
+pub mod ics;
+pub mod markdown;
+pub mod taskwarrior;
+pub mod todotxt;
+
+/// File formats tasks can be imported from and exported to
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum Format {
+    /// One task per line as described at todotxt.org
+    Todotxt,
+    /// JSON as written by `task export`
+    Taskwarrior,
+    /// Comma separated values with a header row
+    Csv,
+    /// iCalendar with a VTODO per task
+    Ics,
+    /// GitHub-style checklist
+    Markdown,
+}
+
+/// UTC date-times as used by Taskwarrior and iCalendar
+const UTC_FORMAT: &str = "%Y%m%dT%H%M%SZ";
+/// Length of a stored timestamp, e.g. `05-01-2024 14:30:00`
+const TIMESTAMP_LENGTH: usize = 19;
+
+/// Settings of formats that need them
+#[derive(Debug, Default)]
+pub struct ImportOptions {
+    /// Column to field mappings, e.g. `Due Date=due`
+    pub map: Vec<String>,
+    /// Format of dates in the file
+    pub date_format: String,
+}
+
+/// Settings of formats that need them when writing
+#[derive(Debug, Default)]
+pub struct ExportOptions {
+    /// Format of dates where the format doesn't prescribe one
+    pub date_format: String,
+    /// Write a section per tag
+    pub group_by_tag: bool,
+}
+
+/// Tasks read from a file, along with the entries that couldn't be read
+#[derive(Debug, Default)]
+pub struct ParsedTasks {
+    pub tasks: Vec<ImportedTask>,
+    pub errors: Vec<String>,
+}
+
+/// Task fields read from another format, before the task is created
+#[derive(Debug, Default, Clone)]
+pub struct ImportedTask {
+    /// Line of the file the task was read from
+    pub line: Option<usize>,
+    pub name: String,
+    pub uuid: Option<String>,
+    pub priority: Priority,
+    pub due_date: Option<NaiveDate>,
+    pub tags: Vec<String>,
+    pub done: bool,
+    pub cancelled: bool,
+    /// Being worked on, for formats that can only tell that much
+    pub started: bool,
+    /// Workflow state for formats that keep it, takes precedence over `done`,
+    /// `cancelled` and `started`
+    pub state: Option<String>,
+    pub created_at: Option<NaiveDateTime>,
+    pub updated_at: Option<NaiveDateTime>,
+    pub completed_at: Option<NaiveDateTime>,
+    pub annotations: Vec<Annotation>,
+    pub notes: Option<String>,
+}
+
+/// Read all tasks from `input` in given format, entries that can't be read
+/// are reported rather than failing the whole file
+pub fn parse_tasks(format: Format, input: &str, options: &ImportOptions) -> TodoResult<ParsedTasks> {
+    match format {
+        Format::Todotxt => Ok(todotxt::parse(input)),
+        Format::Taskwarrior => taskwarrior::parse(input),
+        Format::Csv => csv::parse(input, options),
+        Format::Ics => ics::parse(input),
+        Format::Markdown => Ok(markdown::parse(input, options)),
+    }
+}
+
+/// Write given tasks in given format
+pub fn format_tasks(format: Format, tasks: &[&Task], workflow: &Workflow, options: &ExportOptions) -> TodoResult<String> {
+    match format {
+        Format::Todotxt => Ok(todotxt::format(tasks)),
+        Format::Taskwarrior => taskwarrior::format(tasks, workflow),
+        Format::Csv => csv::format(tasks, &options.date_format),
+        Format::Ics => Ok(ics::format(tasks, workflow)),
+        Format::Markdown => Ok(markdown::format(tasks, workflow, options)),
+    }
+}
+
+/// Read a UTC date-time as local time
+fn parse_utc(date: &str) -> Option<NaiveDateTime> {
+    let date = NaiveDateTime::parse_from_str(date, UTC_FORMAT).ok()?;
+    Some(Utc.from_utc_datetime(&date).with_timezone(&Local).naive_local())
+}
+
+/// Write a local time as UTC date-time
+fn format_utc(date: NaiveDateTime) -> Option<String> {
+    let date = Local.from_local_datetime(&date).earliest()?;
+    Some(date.with_timezone(&Utc).format(UTC_FORMAT).to_string())
+}
+
+fn format_utc_timestamp(timestamp: &str) -> Option<String> {
+    format_utc(parse_timestamp(timestamp)?)
+}
+
+/// Write annotations one per line as `DD-MM-YYYY HH:MM:SS text`
+fn format_annotations(annotations: &[Annotation]) -> String {
+    annotations
+        .iter()
+        .map(|annotation| format!("{} {}", annotation.at, annotation.text))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Read annotations written by `format_annotations`, lines without a
+/// timestamp continue the annotation before them
+fn parse_annotations(text: &str) -> Vec<Annotation> {
+    let mut annotations: Vec<Annotation> = vec![];
+    for line in text.lines() {
+        let stamped = annotation_timestamp(line).map(|at| (at, line[TIMESTAMP_LENGTH..].trim_start()));
+        match (stamped, annotations.last_mut()) {
+            (Some((at, text)), _) => annotations.push(Annotation {
+                at: at.to_string(),
+                text: text.to_string(),
+            }),
+            (None, Some(last)) => {
+                last.text.push('\n');
+                last.text.push_str(line);
+            }
+            (None, None) => annotations.push(Annotation {
+                at: timestamp(),
+                text: line.to_string(),
+            }),
+        }
+    }
+    annotations
+}
+
+/// Timestamp an annotation line written by `format_annotations` starts with
+fn annotation_timestamp(line: &str) -> Option<&str> {
+    line.get(..TIMESTAMP_LENGTH).filter(|at| parse_timestamp(at).is_some())
+}
@@ -0,0 +1,232 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+
+use super::{format_utc, format_utc_timestamp, parse_utc, ImportedTask, ParsedTasks};
+use crate::app::{Workflow, DEFAULT_TIMESTAMP_FORMAT};
+use crate::{Annotation, Priority, Task, TodoResult};
+
+/// Fields of a task as written by `task export`, others are ignored
+#[derive(Serialize, Deserialize)]
+struct TaskwarriorTask {
+    uuid: Option<String>,
+    description: String,
+    status: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    entry: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    modified: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    end: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    due: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    priority: Option<String>,
+    #[serde(skip_serializing)]
+    project: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    tags: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    annotations: Vec<TaskwarriorAnnotation>,
+    /// User defined attribute, Taskwarrior keeps attributes it doesn't know
+    #[serde(skip_serializing_if = "Option::is_none")]
+    notes: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct TaskwarriorAnnotation {
+    entry: String,
+    description: String,
+}
+
+/// Parse a JSON array of tasks, or one task per line as older versions export
+pub fn parse(input: &str) -> TodoResult<ParsedTasks> {
+    let tasks: Vec<TaskwarriorTask> = match input.trim_start().starts_with('[') {
+        true => serde_json::from_str(input)?,
+        false => input
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(number, line)| serde_json::from_str(line).map_err(|e| format!("line {}: {}", number + 1, e)))
+            .collect::<Result<_, _>>()?,
+    };
+    let mut parsed = ParsedTasks::default();
+    for task in tasks {
+        match to_imported(task) {
+            Ok(task) => parsed.tasks.push(task),
+            Err(e) => parsed.errors.push(e),
+        }
+    }
+    Ok(parsed)
+}
+
+fn to_imported(task: TaskwarriorTask) -> Result<ImportedTask, String> {
+    let description = task.description.clone();
+    let context = |e: String| format!("task {:?}: {}", description, e);
+    let mut tags = task.project.into_iter().collect::<Vec<_>>();
+    tags.extend(task.tags);
+    Ok(ImportedTask {
+        name: task.description,
+        uuid: task.uuid,
+        priority: match task.priority.as_deref() {
+            Some("H") => Priority::High,
+            Some("M") => Priority::Medium,
+            Some("L") => Priority::Low,
+            _ => Priority::None,
+        },
+        due_date: parse_date(task.due.as_deref()).map_err(context)?.map(|due| due.date()),
+        tags,
+        // waiting and recurring tasks are still to be done
+        done: task.status == "completed",
+        cancelled: task.status == "deleted",
+        created_at: parse_date(task.entry.as_deref()).map_err(context)?,
+        updated_at: parse_date(task.modified.as_deref()).map_err(context)?,
+        completed_at: parse_date(task.end.as_deref()).map_err(context)?,
+        annotations: task
+            .annotations
+            .into_iter()
+            .map(|annotation| {
+                Ok(Annotation {
+                    at: parse_date(Some(&annotation.entry))
+                        .map_err(context)?
+                        .map(|at| at.format(DEFAULT_TIMESTAMP_FORMAT).to_string())
+                        .unwrap_or_default(),
+                    text: annotation.description,
+                })
+            })
+            .collect::<Result<_, String>>()?,
+        notes: task.notes.filter(|notes| !notes.is_empty()),
+        ..Default::default()
+    })
+}
+
+/// Write tasks as a JSON array
+pub fn format(tasks: &[&Task], workflow: &Workflow) -> TodoResult<String> {
+    let tasks = tasks
+        .iter()
+        .map(|task| to_taskwarrior(task, workflow))
+        .collect::<Vec<_>>();
+    Ok(format!("{}\n", serde_json::to_string_pretty(&tasks)?))
+}
+
+fn to_taskwarrior(task: &Task, workflow: &Workflow) -> TaskwarriorTask {
+    let status = if task.done {
+        "completed"
+    } else if task.state == workflow.cancelled {
+        "deleted"
+    } else {
+        "pending"
+    };
+    TaskwarriorTask {
+        uuid: Some(task.uuid.clone()),
+        description: task.name.clone(),
+        status: status.to_string(),
+        entry: format_utc_timestamp(&task.created_at),
+        modified: task.updated_at.as_deref().and_then(format_utc_timestamp),
+        end: task.completed_at.as_deref().and_then(format_utc_timestamp),
+        due: task
+            .get_parsed_due_date()
+            .and_then(|due| format_utc(due.and_hms_opt(0, 0, 0)?)),
+        // Taskwarrior has no level above high
+        priority: match task.priority {
+            Priority::None => None,
+            Priority::Low => Some("L".to_string()),
+            Priority::Medium => Some("M".to_string()),
+            Priority::High | Priority::Critical => Some("H".to_string()),
+        },
+        project: None,
+        tags: task.tags.clone().unwrap_or_default(),
+        annotations: task
+            .annotations
+            .iter()
+            .map(|annotation| TaskwarriorAnnotation {
+                entry: format_utc_timestamp(&annotation.at).unwrap_or_default(),
+                description: annotation.text.clone(),
+            })
+            .collect(),
+        notes: task.notes.clone(),
+    }
+}
+
+/// Read a UTC date as local time
+fn parse_date(date: Option<&str>) -> Result<Option<NaiveDateTime>, String> {
+    match date {
+        Some(date) => parse_utc(date).map(Some).ok_or_else(|| format!("invalid date {}", date)),
+        None => Ok(None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DEFAULT_DATE_FORMAT;
+
+    fn timestamp(at: Option<NaiveDateTime>) -> Option<String> {
+        at.map(|at| at.format(DEFAULT_TIMESTAMP_FORMAT).to_string())
+    }
+
+    #[test]
+    fn round_trips_tasks() {
+        let workflow = Workflow::default();
+        let mut task = Task::new(
+            1,
+            "Write \"report\"".to_string(),
+            Priority::Medium,
+            Some("06-01-2024".to_string()),
+            Some(vec!["work".to_string(), "work/api".to_string()]),
+            DEFAULT_DATE_FORMAT,
+            &workflow,
+        )
+        .unwrap();
+        task.created_at = "02-01-2024 09:15:00".to_string();
+        task.notes = Some("first line\nsecond line".to_string());
+        task.annotations = vec![Annotation {
+            at: "03-01-2024 10:00:00".to_string(),
+            text: "asked for numbers".to_string(),
+        }];
+        let mut cancelled = task.clone();
+        cancelled.uuid = "0c3e7d2e-6a8b-4e8f-9b1e-3d2f4a5b6c7d".to_string();
+        cancelled.move_to(&workflow.cancelled, &workflow).unwrap();
+        let mut done = task.clone();
+        done.uuid = "1d4f8e3f-7b9c-4f90-8c2f-4e3a5b6c7d8e".to_string();
+        done.move_to(&workflow.done, &workflow).unwrap();
+
+        let parsed = parse(&format(&[&task, &cancelled, &done], &workflow).unwrap()).unwrap();
+        assert!(parsed.errors.is_empty(), "{:?}", parsed.errors);
+        let [imported, imported_cancelled, imported_done] = &parsed.tasks[..] else {
+            panic!("expected 3 tasks, got {}", parsed.tasks.len());
+        };
+        assert_eq!(imported.name, task.name);
+        assert_eq!(imported.uuid.as_deref(), Some(task.uuid.as_str()));
+        assert_eq!(imported.priority, Priority::Medium);
+        assert_eq!(imported.due_date, task.get_parsed_due_date());
+        assert_eq!(imported.tags, vec!["work", "work/api"]);
+        assert_eq!(imported.notes, task.notes);
+        assert_eq!(timestamp(imported.created_at).as_ref(), Some(&task.created_at));
+        assert_eq!(imported.annotations.len(), 1);
+        assert_eq!(imported.annotations[0].at, "03-01-2024 10:00:00");
+        assert_eq!(imported.annotations[0].text, "asked for numbers");
+        assert!(!imported.done && !imported.cancelled);
+
+        assert!(imported_cancelled.cancelled && !imported_cancelled.done);
+        assert_eq!(imported_cancelled.uuid.as_deref(), Some(cancelled.uuid.as_str()));
+
+        assert!(imported_done.done && !imported_done.cancelled);
+        assert_eq!(timestamp(imported_done.completed_at), done.completed_at);
+        assert_eq!(timestamp(imported_done.updated_at), done.updated_at);
+    }
+
+    #[test]
+    fn reads_one_task_per_line() {
+        let input = concat!(
+            r#"{"uuid":"a","description":"one","status":"pending","project":"home","tags":["x"]}"#,
+            "\n\n",
+            r#"{"description":"two","status":"completed","priority":"H","notes":""}"#,
+        );
+        let parsed = parse(input).unwrap();
+        assert_eq!(parsed.tasks.len(), 2);
+        assert_eq!(parsed.tasks[0].tags, vec!["home", "x"]);
+        assert!(parsed.tasks[1].done);
+        assert_eq!(parsed.tasks[1].priority, Priority::High);
+        assert!(parsed.tasks[1].notes.is_none());
+    }
+}
@@ -0,0 +1,259 @@
+use chrono::NaiveDate;
+
+use super::{ImportedTask, ParsedTasks};
+use crate::{parse_timestamp, Priority, Task, TodoResult};
+
+const DATE_FORMAT: &str = "%Y-%m-%d";
+
+/// Parse todo.txt lines, blank lines are skipped
+pub fn parse(input: &str) -> ParsedTasks {
+    let mut parsed = ParsedTasks::default();
+    for (number, line) in input.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        match parse_line(line) {
+            Ok(mut task) => {
+                task.line = Some(number + 1);
+                parsed.tasks.push(task);
+            }
+            Err(e) => parsed.errors.push(format!("line {}: {}", number + 1, e)),
+        }
+    }
+    parsed
+}
+
+/// Parse a single line, e.g.
+/// `x 2024-01-05 2024-01-02 Call mom +family @phone due:2024-01-06 pri:A`
+fn parse_line(line: &str) -> TodoResult<ImportedTask> {
+    let mut task = ImportedTask::default();
+    let mut words = line.split_whitespace().peekable();
+    if words.peek() == Some(&"x") {
+        words.next();
+        task.done = true;
+        // a completed task may carry a completion date, which then precedes the creation date
+        if let Some(date) = words.peek().and_then(|word| parse_date(word)) {
+            words.next();
+            task.completed_at = date.and_hms_opt(0, 0, 0);
+        }
+    } else if let Some(priority) = words.peek().and_then(|word| parse_priority(word)) {
+        words.next();
+        task.priority = priority;
+    }
+    if let Some(date) = words.peek().and_then(|word| parse_date(word)) {
+        words.next();
+        task.created_at = date.and_hms_opt(0, 0, 0);
+    }
+    let mut name = vec![];
+    for word in words {
+        if let Some(word) = word.strip_prefix('\\') {
+            // escaped by `format`, see `escape_word`
+            name.push(word);
+        } else if let Some(project) = word.strip_prefix('+').filter(|project| !project.is_empty()) {
+            task.tags.push(project.to_string());
+        } else if word.len() > 1 && word.starts_with('@') {
+            task.tags.push(word.to_string());
+        } else if let Some(due) = word.strip_prefix("due:") {
+            task.due_date = Some(parse_date(due).ok_or_else(|| format!("invalid due date {}", due))?);
+        } else if let Some(uuid) = word.strip_prefix("uuid:").filter(|uuid| !uuid.is_empty()) {
+            task.uuid = Some(uuid.to_string());
+        } else if let Some(priority) = word.strip_prefix("pri:") {
+            // completed tasks keep their priority as a key-value
+            task.priority = parse_priority(&format!("({})", priority))
+                .ok_or_else(|| format!("invalid priority {}", priority))?;
+        } else {
+            name.push(word);
+        }
+    }
+    if name.is_empty() {
+        return Err("task has no description".into());
+    }
+    task.name = name.join(" ");
+    Ok(task)
+}
+
+/// Write one line per task, in a form `parse` reads back into the same task
+pub fn format(tasks: &[&Task]) -> String {
+    tasks
+        .iter()
+        .map(|task| format!("{}\n", format_line(task)))
+        .collect()
+}
+
+fn format_line(task: &Task) -> String {
+    let mut words = vec![];
+    let date = |timestamp: &str| parse_timestamp(timestamp).map(|at| at.format(DATE_FORMAT).to_string());
+    let created_on = date(&task.created_at);
+    if task.done {
+        words.push("x".to_string());
+        // the creation date can only be given after a completion date
+        if let Some(completed_on) = task.completed_at.as_deref().and_then(date) {
+            words.push(completed_on);
+            words.extend(created_on);
+        }
+    } else {
+        words.extend(priority_letter(task.priority).map(|letter| format!("({})", letter)));
+        words.extend(created_on);
+    }
+    let name = task.name.split_whitespace().enumerate();
+    words.extend(name.map(|(index, word)| escape_word(word, index == 0)));
+    for tag in task.tags.iter().flatten() {
+        // tags can't contain whitespace in todo.txt
+        let tag = tag.split_whitespace().collect::<Vec<_>>().join("_");
+        match tag.starts_with('@') {
+            true => words.push(tag),
+            false => words.push(format!("+{}", tag)),
+        }
+    }
+    if let Some(due_date) = task.get_parsed_due_date() {
+        words.push(format!("due:{}", due_date.format(DATE_FORMAT)));
+    }
+    if task.done {
+        words.extend(priority_letter(task.priority).map(|letter| format!("pri:{}", letter)));
+    }
+    words.push(format!("uuid:{}", task.uuid));
+    words.join(" ")
+}
+
+/// Prefix a word of a task name with a backslash if `parse_line` would read
+/// it as a tag, a key-value or, as the first word, a marker, priority or date
+fn escape_word(word: &str, first: bool) -> String {
+    let special = word.starts_with('\\')
+        || (word.len() > 1 && (word.starts_with('+') || word.starts_with('@')))
+        || ["due:", "pri:", "uuid:"].iter().any(|key| word.starts_with(key))
+        || (first && (word == "x" || parse_priority(word).is_some() || parse_date(word).is_some()));
+    match special {
+        true => format!("\\{}", word),
+        false => word.to_string(),
+    }
+}
+
+fn parse_date(word: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(word, DATE_FORMAT).ok()
+}
+
+/// `(A)` is critical, `(B)` high, `(C)` medium and `(D)` to `(Z)` low
+fn parse_priority(word: &str) -> Option<Priority> {
+    let letter = word.strip_prefix('(')?.strip_suffix(')')?;
+    match letter {
+        "A" => Some(Priority::Critical),
+        "B" => Some(Priority::High),
+        "C" => Some(Priority::Medium),
+        _ if letter.len() == 1 && letter.chars().all(|c| c.is_ascii_uppercase()) => Some(Priority::Low),
+        _ => None,
+    }
+}
+
+fn priority_letter(priority: Priority) -> Option<char> {
+    match priority {
+        Priority::None => None,
+        Priority::Low => Some('D'),
+        Priority::Medium => Some('C'),
+        Priority::High => Some('B'),
+        Priority::Critical => Some('A'),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Workflow, DEFAULT_DATE_FORMAT};
+
+    fn task(name: &str) -> Task {
+        Task::new(1, name.to_string(), Priority::None, None, None, DEFAULT_DATE_FORMAT, &Workflow::default()).unwrap()
+    }
+
+    fn round_trip(task: &Task) -> ImportedTask {
+        let parsed = parse(&format(&[task]));
+        assert!(parsed.errors.is_empty(), "{:?}", parsed.errors);
+        assert_eq!(parsed.tasks.len(), 1);
+        parsed.tasks.into_iter().next().unwrap()
+    }
+
+    #[test]
+    fn parses_a_full_line() {
+        let task = parse_line("x 2024-01-05 2024-01-02 Call mom +family @phone due:2024-01-06 pri:A uuid:abc").unwrap();
+        assert!(task.done);
+        assert_eq!(task.name, "Call mom");
+        assert_eq!(task.tags, vec!["family", "@phone"]);
+        assert_eq!(task.due_date, NaiveDate::from_ymd_opt(2024, 1, 6));
+        assert_eq!(task.priority, Priority::Critical);
+        assert_eq!(task.uuid.as_deref(), Some("abc"));
+        assert_eq!(task.completed_at, NaiveDate::from_ymd_opt(2024, 1, 5).unwrap().and_hms_opt(0, 0, 0));
+        assert_eq!(task.created_at, NaiveDate::from_ymd_opt(2024, 1, 2).unwrap().and_hms_opt(0, 0, 0));
+    }
+
+    #[test]
+    fn reads_escaped_words_as_name() {
+        let task = parse_line(r"\x \(A) \2024-01-01 \+1 \@bob \due:soon \\here").unwrap();
+        assert_eq!(task.name, r"x (A) 2024-01-01 +1 @bob due:soon \here");
+        assert!(!task.done);
+        assert_eq!(task.priority, Priority::None);
+        assert!(task.created_at.is_none());
+        assert!(task.tags.is_empty());
+        assert!(task.due_date.is_none());
+    }
+
+    #[test]
+    fn escapes_special_words() {
+        assert_eq!(escape_word("x", true), r"\x");
+        assert_eq!(escape_word("x", false), "x");
+        assert_eq!(escape_word("(A)", true), r"\(A)");
+        assert_eq!(escape_word("(A)", false), "(A)");
+        assert_eq!(escape_word("2024-01-01", true), r"\2024-01-01");
+        assert_eq!(escape_word("+1", false), r"\+1");
+        assert_eq!(escape_word("@bob", false), r"\@bob");
+        assert_eq!(escape_word("uuid:z", false), r"\uuid:z");
+        assert_eq!(escape_word(r"\the", false), r"\\the");
+        assert_eq!(escape_word("+", false), "+");
+        assert_eq!(escape_word("@", false), "@");
+    }
+
+    #[test]
+    fn keeps_names_that_look_like_syntax() {
+        for name in [
+            "Email @bob about +1 and due:tomorrow",
+            r"x marks \the spot",
+            "2024-01-01 review (A) pri:B uuid:z",
+            "(A) first",
+            "+ and @ alone",
+        ] {
+            let imported = round_trip(&task(name));
+            assert_eq!(imported.name, name);
+            assert!(imported.tags.is_empty());
+            assert!(imported.due_date.is_none());
+            assert!(!imported.done);
+            assert_eq!(imported.priority, Priority::None);
+        }
+    }
+
+    #[test]
+    fn round_trips_tasks() {
+        let workflow = Workflow::default();
+        let mut open = Task::new(
+            1,
+            "Call mom".to_string(),
+            Priority::High,
+            Some("06-01-2024".to_string()),
+            Some(vec!["family".to_string(), "@phone".to_string(), "side project".to_string()]),
+            DEFAULT_DATE_FORMAT,
+            &workflow,
+        )
+        .unwrap();
+        let imported = round_trip(&open);
+        assert_eq!(imported.name, "Call mom");
+        assert_eq!(imported.priority, Priority::High);
+        assert_eq!(imported.due_date, NaiveDate::from_ymd_opt(2024, 1, 6));
+        assert_eq!(imported.tags, vec!["family", "@phone", "side_project"]);
+        assert_eq!(imported.uuid.as_deref(), Some(open.uuid.as_str()));
+        assert!(!imported.done);
+
+        open.move_to(&workflow.done, &workflow).unwrap();
+        let imported = round_trip(&open);
+        assert!(imported.done);
+        assert_eq!(imported.priority, Priority::High);
+        assert_eq!(imported.name, "Call mom");
+        assert!(imported.completed_at.is_some());
+        assert!(imported.created_at.is_some());
+    }
+}
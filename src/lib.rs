@@ -1,11 +1,19 @@
 pub use crate::tasks::task_manager;
-use clap::Parser;
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use colored::Colorize;
+use serde::Serialize;
 use std::error::Error; // Import the task_manager module
+use std::fs;
+use std::io::{self, Write};
+use std::path::PathBuf;
 
 mod app;
+mod formats;
 mod tasks;
+mod tui;
 
 pub use app::*;
+pub use formats::*;
 pub use tasks::*;
 
 #[derive(Parser)]
@@ -18,6 +26,15 @@ pub use tasks::*;
 pub struct Cli {
     #[clap(subcommand)]
     subcmd: Option<SubCommands>,
+    /// Print only the ids of affected tasks
+    #[clap(short, long, global = true)]
+    quiet: bool,
+    /// Print the whole task list after a change
+    #[clap(long, global = true, conflicts_with = "quiet")]
+    show_list: bool,
+    /// Number listed tasks @1, @2, ... to refer to them by until the next listing
+    #[clap(long, global = true)]
+    display_ids: bool,
 }
 
 #[derive(Parser, Debug)]
@@ -26,9 +43,12 @@ pub enum SubCommands {
     Add {
         /// Name of the task
         name: String,
-        /// Mark the task as high priority
+        /// Priority of the task: none, low, medium, high, critical or P0-P4
         #[clap(short, long)]
-        priority: bool,
+        priority: Option<Priority>,
+        /// Give the task high priority, same as `--priority high`
+        #[clap(long, conflicts_with = "priority")]
+        high: bool,
         /// Due date of the task (format: DD-MM-YYYY HH:MM:SS)
         #[clap(short, long)]
         due_date: Option<String>,
@@ -36,172 +56,867 @@ pub enum SubCommands {
         #[clap(short, long, value_delimiter = ',',  num_args = 1..)]
         tags: Option<Vec<String>>,
     },
-    /// Edit a task in the todo list
+    /// Edit tasks in the todo list
     Edit {
-        /// ID of the task to edit
-        task_id: String,
+        #[clap(flatten)]
+        selection: Selection,
         /// Update name
         #[clap(short, long)]
         name: Option<String>,
-        /// Update priority
+        /// Update priority: none, low, medium, high, critical or P0-P4
         #[clap(short, long)]
-        priority: Option<bool>,
+        priority: Option<Priority>,
         /// Update due date (format: DD-MM-YYYY)
         #[clap(short, long)]
         due_date: Option<String>,
-        /// Update tags
+        /// Replace all tags
         #[clap(short, long, value_delimiter = ',',  num_args = 1..)]
         tags: Option<Vec<String>>,
+        /// Add tag(s) keeping the existing ones
+        #[clap(long, value_delimiter = ',',  num_args = 1..)]
+        add_tag: Vec<String>,
+        /// Remove tag(s) keeping the other ones
+        #[clap(long, value_delimiter = ',',  num_args = 1..)]
+        remove_tag: Vec<String>,
+        /// Remove all tags
+        #[clap(long, conflicts_with = "tags")]
+        clear_tags: bool,
         /// Update completion status
         #[clap(short, long)]
         complete: Option<bool>,
+        /// Move to a workflow state
+        #[clap(short, long, conflicts_with = "complete")]
+        state: Option<String>,
+        /// Edit the task in $EDITOR, conflicts with all other options
+        #[clap(short, long, conflicts_with_all = &["name", "priority", "due_date", "tags", "add_tag", "remove_tag", "clear_tags", "complete", "state"])]
+        interactive: bool,
     },
     /// List tasks in the todo list, by default it won't list completed tasks
     List {
-        /// List only high priority tasks
+        /// List only tasks matching given priority (e.g. high, '>=medium')
         #[clap(short, long)]
-        priority: bool,
+        priority: Option<PriorityFilter>,
+        /// List only tasks of high priority or above, same as `--priority '>=high'`
+        #[clap(long, conflicts_with = "priority")]
+        high: bool,
 
         /// List only tasks that are due before given date (format: DD-MM-YYYY HH:MM:SS)
         #[clap(short, long)]
         due_before: Option<String>,
 
-        /// List only tasks belonging to given tag(s)
-        #[clap(short, long, value_delimiter = ',',  num_args = 1..)]
+        /// List only tasks belonging to all of given tag(s)
+        #[clap(short, long, visible_alias = "tags-all", value_delimiter = ',',  num_args = 1..)]
         tags: Option<Vec<String>>,
 
+        /// List only tasks belonging to any of given tag(s)
+        #[clap(long, value_delimiter = ',',  num_args = 1..)]
+        tags_any: Option<Vec<String>>,
+
+        /// List only tasks belonging to none of given tag(s)
+        #[clap(long, value_delimiter = ',',  num_args = 1..)]
+        exclude_tags: Option<Vec<String>>,
+
         /// List only tasks that are done
         #[clap(long)]
         done: bool,
 
+        /// List only tasks in given workflow state
+        #[clap(short, long, conflicts_with = "done")]
+        state: Option<String>,
+
+        /// List only tasks completed on or after given date (e.g. yesterday, last-week, 3d, DD-MM-YYYY)
+        #[clap(long, requires = "done")]
+        since: Option<String>,
+
         /// List all tasks, conflicts with all other filters
-        #[clap(long, conflicts_with_all = &["priority", "due_before", "tags", "done"])]
+        #[clap(long, conflicts_with_all = &["priority", "high", "due_before", "tags", "tags_any", "exclude_tags", "done", "state", "since"])]
         all: bool,
+
+        /// List archived tasks instead, conflicts with all other filters
+        #[clap(long, conflicts_with_all = &["priority", "high", "due_before", "tags", "tags_any", "exclude_tags", "done", "state", "since", "all"])]
+        archived: bool,
+    },
+    /// Search tasks by words and filter terms (e.g. 'milk tag:groceries')
+    Search {
+        /// Words and `key:value` terms a task has to match
+        query: String,
+        /// Search archived tasks too
+        #[clap(long)]
+        include_archive: bool,
+    },
+    /// Import tasks from a file in another format
+    Import {
+        /// Format of the file
+        #[clap(short, long, value_enum)]
+        format: Format,
+        /// File to read, standard input if not given or `-`
+        file: Option<PathBuf>,
+        /// Show the tasks that would be imported without adding them
+        #[clap(long)]
+        dry_run: bool,
+        /// Map CSV columns to fields name, priority, due, tags, done, notes, uuid, state, created, completed and annotations (e.g. 'Task=name,Due Date=due')
+        #[clap(long, value_delimiter = ',')]
+        map: Vec<String>,
+        /// Format of dates in a CSV or markdown file, the configured date format by default
+        #[clap(long)]
+        date_format: Option<String>,
+    },
+    /// Export all tasks to standard output in another format
+    Export {
+        /// Format to write
+        #[clap(short, long, value_enum)]
+        format: Format,
+        /// Format of dates in CSV and markdown, the configured date format by default
+        #[clap(long)]
+        date_format: Option<String>,
+        /// Write a section per tag in markdown
+        #[clap(long)]
+        group_by_tag: bool,
     },
-    /// Mark a task as done
+    /// Save all data to a versioned backup file
+    Backup {
+        /// File to write, a timestamped file in ~/.msa_todo_rust/backups if not given
+        path: Option<PathBuf>,
+    },
+    /// Restore data from a backup file, after backing up the current data
+    Restore {
+        /// Backup file to read
+        path: PathBuf,
+        /// Replace all current data or only add the tasks missing from it
+        #[clap(short, long, value_enum, default_value = "replace")]
+        mode: RestoreMode,
+        /// Don't ask for confirmation before replacing data
+        #[clap(short, long)]
+        yes: bool,
+    },
+    /// Move done tasks to the archive
+    Archive {
+        /// Archive only tasks completed longer ago than this (e.g. 14d, 2w)
+        #[clap(long)]
+        older_than: Option<String>,
+    },
+    /// Mark tasks as done
     Done {
-        /// ID of the task to mark as done
+        #[clap(flatten)]
+        selection: Selection,
+    },
+    /// Move done or cancelled tasks back to the initial state
+    Reopen {
+        #[clap(flatten)]
+        selection: Selection,
+    },
+    /// Mark tasks as started
+    Start {
+        #[clap(flatten)]
+        selection: Selection,
+    },
+    /// Mark tasks as cancelled
+    Cancel {
+        #[clap(flatten)]
+        selection: Selection,
+    },
+    /// Move tasks to a workflow state
+    Move {
+        #[clap(flatten)]
+        selection: Selection,
+        /// State to move the tasks to
+        #[clap(long)]
+        to: String,
+    },
+    /// Append a timestamped note to a task
+    Note {
+        /// ID or UUID prefix of the task
         task_id: String,
+        /// Text of the note, may span multiple lines
+        text: String,
     },
-    /// Remove a task from the todo list
-    Remove {
-        /// ID of the task to remove
+    /// Show a single task in full with its notes, history and related tasks
+    Show {
+        /// ID or UUID prefix of the task
         task_id: String,
+        /// Output format
+        #[clap(short, long, value_enum, default_value = "card")]
+        output: ShowFormat,
+    },
+    /// Remove tasks from the todo list
+    Remove {
+        #[clap(flatten)]
+        selection: Selection,
     },
-    /// List all existing tags
-    Tags,
-    /// Reset the todo list
-    Reset,
+    /// List all existing tags with their task counts, or manage them
+    Tags {
+        #[clap(subcommand)]
+        action: Option<TagCommands>,
+    },
+    /// Remove all tasks, or the ones in given scope, after taking a backup
+    Reset {
+        /// Remove only tasks that are done
+        #[clap(long)]
+        done_only: bool,
+        /// Remove only tasks carrying given tag or one nested below it
+        #[clap(long)]
+        tag: Option<String>,
+        /// Remove only tasks created before given date (format: DD-MM-YYYY)
+        #[clap(long)]
+        before: Option<String>,
+        /// Don't ask for confirmation
+        #[clap(short, long)]
+        yes: bool,
+    },
+    /// Open the interactive full-screen interface
+    Tui,
+    /// Give open tasks compact ids
+    Renumber {
+        /// Renumber done and cancelled tasks too
+        #[clap(long)]
+        all: bool,
+    },
+    /// Check stored data for inconsistencies
+    Doctor {
+        /// Rebuild derived data to fix the problems found
+        #[clap(long)]
+        fix: bool,
+    },
+}
+
+impl SubCommands {
+    /// Whether the command is used to recover data, which works with a
+    /// config that is otherwise refused
+    fn recovers_data(&self) -> bool {
+        matches!(
+            self,
+            SubCommands::Backup { .. } | SubCommands::Restore { .. } | SubCommands::Doctor { .. }
+        )
+    }
+
+    /// Whether the command changes tasks, which is when done tasks are
+    /// archived as configured
+    fn changes_tasks(&self) -> bool {
+        match self {
+            SubCommands::Add { .. }
+            | SubCommands::Edit { .. }
+            | SubCommands::Done { .. }
+            | SubCommands::Reopen { .. }
+            | SubCommands::Start { .. }
+            | SubCommands::Cancel { .. }
+            | SubCommands::Move { .. }
+            | SubCommands::Note { .. }
+            | SubCommands::Remove { .. }
+            | SubCommands::Reset { .. }
+            | SubCommands::Renumber { .. }
+            | SubCommands::Tui => true,
+            SubCommands::Import { dry_run, .. } => !dry_run,
+            SubCommands::Tags { action } => action.is_some(),
+            SubCommands::List { .. }
+            | SubCommands::Search { .. }
+            | SubCommands::Export { .. }
+            | SubCommands::Show { .. }
+            | SubCommands::Backup { .. }
+            | SubCommands::Restore { .. }
+            | SubCommands::Archive { .. }
+            | SubCommands::Doctor { .. } => false,
+        }
+    }
+}
+
+#[derive(Subcommand, Debug)]
+pub enum TagCommands {
+    /// Rename a tag on every task
+    Rename {
+        /// Current name of the tag
+        tag: String,
+        /// New name of the tag
+        new_name: String,
+    },
+    /// Merge tags into a single one
+    Merge {
+        /// Tags to merge
+        #[clap(required = true)]
+        tags: Vec<String>,
+        /// Tag to merge into, may be new or one of the merged tags
+        #[clap(long)]
+        into: String,
+    },
+    /// Remove a tag from every task
+    Delete {
+        /// Name of the tag
+        tag: String,
+    },
+    /// Set the description of a tag, an empty description removes it
+    Describe {
+        /// Name of the tag
+        tag: String,
+        /// Description of the tag
+        description: String,
+    },
+    /// Set the color a tag is listed with, an empty color removes it
+    Color {
+        /// Name of the tag
+        tag: String,
+        /// Color name (e.g. red, bright blue)
+        color: String,
+    },
+}
+
+/// How `todo restore` combines a backup with the current data
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum RestoreMode {
+    Replace,
+    Merge,
+}
+
+/// Output of `todo show`
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum ShowFormat {
+    Card,
+    Json,
+}
+
+/// A task as written by `todo show --output json`, with the ids of related tasks
+#[derive(Serialize)]
+struct TaskDetails<'a> {
+    #[serde(flatten)]
+    task: &'a Task,
+    related: Vec<usize>,
+}
+
+/// Tasks targeted by a command, given either as ids or as a filter
+#[derive(Args, Debug)]
+pub struct Selection {
+    /// ID(s) or UUID prefix(es) of the task(s), a list and ranges are allowed (e.g. 3,5,8-12)
+    #[clap(required_unless_present = "filter")]
+    task_ids: Option<String>,
+    /// Select tasks matching a filter instead of ids (e.g. 'tag:work done:false')
+    #[clap(long = "where", conflicts_with = "task_ids")]
+    filter: Option<String>,
+    /// Don't ask for confirmation before changing multiple tasks
+    #[clap(short, long)]
+    yes: bool,
 }
 
 pub type TodoResult<T> = Result<T, Box<dyn Error>>;
 
-pub fn get_args() -> TodoResult<SubCommands> {
-    Ok(Cli::parse().subcmd.unwrap_or_else(|| SubCommands::List {
-        priority: false,
-        due_before: None,
-        tags: None,
-        done: false,
-        all: false,
-    }))
+pub fn get_args() -> TodoResult<Cli> {
+    Ok(Cli::parse())
 }
 
-pub fn run(config: SubCommands) -> TodoResult<()> {
+pub fn run(cli: Cli) -> TodoResult<()> {
     let app_data = AppData::init()?;
+    // recovering from a broken config needs the commands that don't depend on it
+    if !cli.subcmd.as_ref().is_some_and(SubCommands::recovers_data) {
+        app_data.check_config()?;
+    }
     let mut task_manager = task_manager::TaskManager::new(app_data);
-    match config {
+    // commands only reading data leave it as it is, and those needed to
+    // recover from a bad config keep working
+    if cli.subcmd.as_ref().is_some_and(SubCommands::changes_tasks) {
+        task_manager.auto_archive()?;
+    }
+    let feedback = Feedback {
+        quiet: cli.quiet,
+        show_list: cli.show_list,
+        display_ids: cli.display_ids,
+    };
+    match cli.subcmd.unwrap_or(SubCommands::List {
+        priority: None,
+        high: false,
+        due_before: None,
+        tags: None,
+        tags_any: None,
+        exclude_tags: None,
+        done: false,
+        state: None,
+        since: None,
+        all: false,
+        archived: false,
+    }) {
         SubCommands::Add {
             name,
             priority,
+            high,
             due_date,
             tags,
         } => {
-            task_manager.add_task(name, priority, due_date, tags)?;
-            let tasks: Vec<&Task> = task_manager
-                .app_data
-                .tasks
-                .values()
-                .map(|x| x)
-                .collect::<Vec<_>>();
-            print_tasks(&tasks);
+            let priority = match high {
+                true => Priority::High,
+                false => priority.unwrap_or_default(),
+            };
+            let id = task_manager.add_task(name, priority, due_date, tags)?;
+            let changes = describe_changes(&task_manager, &[id], "Added");
+            report(&task_manager, &feedback, changes)?;
         }
         SubCommands::Edit {
-            task_id,
+            selection,
             name,
             priority,
             due_date,
             tags,
+            add_tag,
+            remove_tag,
+            clear_tags,
             complete,
+            state,
+            interactive,
         } => {
-            task_manager.edit_task(
-                parse_id(&task_id)?,
-                name,
-                priority,
-                due_date,
-                tags,
-                complete,
-            )?;
-            let tasks: Vec<&Task> = task_manager
-                .app_data
-                .tasks
-                .values()
-                .map(|x| x)
-                .collect::<Vec<_>>();
-            print_tasks(&tasks);
+            let Some(ids) = select_tasks(&task_manager, &selection, "Edit")? else {
+                return Ok(());
+            };
+            match (interactive, ids.as_slice()) {
+                (true, [id]) => edit_in_editor(&mut task_manager, *id)?,
+                (true, _) => return Err("Interactive editing works on a single task".into()),
+                (false, _) => task_manager.edit_tasks(
+                    &ids,
+                    TaskUpdate {
+                        name,
+                        priority,
+                        due_date,
+                        tags,
+                        add_tags: add_tag,
+                        remove_tags: remove_tag,
+                        clear_tags,
+                        done: complete,
+                        state,
+                        notes: None,
+                    },
+                )?,
+            }
+            let changes = describe_changes(&task_manager, &ids, "Updated");
+            report(&task_manager, &feedback, changes)?;
         }
         SubCommands::List {
             priority,
+            high,
             due_before,
             tags,
+            tags_any,
+            exclude_tags,
             done,
-            all
+            state,
+            since,
+            all,
+            archived,
         } => {
-            let tasks = task_manager.filter_tasks(priority, due_before, tags, done, all)?;
-            print_tasks(&tasks);
-        }
-        SubCommands::Done { task_id } => {
-            task_manager.mark_done(parse_id(&task_id)?)?;
-            let tasks: Vec<&Task> = task_manager
-                .app_data
-                .tasks
-                .values()
-                .map(|x| x)
-                .collect::<Vec<_>>();
-            print_tasks(&tasks);
-        }
-        SubCommands::Remove { task_id } => {
-            task_manager.remove_task(parse_id(&task_id)?)?;
-            let tasks: Vec<&Task> = task_manager
-                .app_data
-                .tasks
-                .values()
-                .map(|x| x)
-                .collect::<Vec<_>>();
-            print_tasks(&tasks);
-        }
-        SubCommands::Tags => {
-            let tags = task_manager.list_tags();
-            display_tags(tags)
-        }
-        SubCommands::Reset => {
-            task_manager.reset_tasks()?;
+            if archived {
+                let archive = read_archive()?;
+                // archived tasks can't be changed, so they get no display ids
+                let feedback = Feedback { display_ids: false, ..feedback };
+                print_listing(&task_manager, &feedback, archive.iter().collect())?;
+                return Ok(());
+            }
+            let tag_filter = TagFilter {
+                all: tags.unwrap_or_default(),
+                any: tags_any.unwrap_or_default(),
+                exclude: exclude_tags.unwrap_or_default(),
+            };
+            let priority = priority.or(high.then(|| PriorityFilter::at_least(Priority::High)));
+            let tasks = task_manager.filter_tasks(priority, due_before, tag_filter, state, done, since, all)?;
+            print_listing(&task_manager, &feedback, tasks)?;
+        }
+        SubCommands::Search {
+            query,
+            include_archive,
+        } => {
+            let query = TaskQuery::parse(&query, &task_manager.app_data.config.date_format)?;
+            let tasks = task_manager
+                .find_tasks(&query)
+                .iter()
+                .map(|id| &task_manager.app_data.tasks[id])
+                .collect();
+            print_listing(&task_manager, &feedback, tasks)?;
+            if include_archive {
+                let archive = read_archive()?;
+                let archived = archive.iter().filter(|task| query.matches(task)).collect::<Vec<_>>();
+                if !feedback.quiet {
+                    println!("\n{}", "Archived".bold());
+                }
+                let feedback = Feedback { display_ids: false, ..feedback };
+                print_listing(&task_manager, &feedback, archived)?;
+            }
+        }
+        SubCommands::Import {
+            format,
+            file,
+            dry_run,
+            map,
+            date_format,
+        } => {
+            let input = match file {
+                Some(file) if file.as_os_str() != "-" => fs::read_to_string(&file)
+                    .map_err(|e| format!("Failed to read {}: {}", file.display(), e))?,
+                _ => io::read_to_string(io::stdin())?,
+            };
+            let options = ImportOptions {
+                map,
+                date_format: date_format.unwrap_or_else(|| task_manager.app_data.config.date_format.clone()),
+            };
+            let parsed = parse_tasks(format, &input, &options)?;
+            let result = task_manager.import_tasks(parsed.tasks, dry_run)?;
+            // entries that couldn't be read don't stop the others from being imported
+            let errors = parsed.errors.iter().chain(&result.errors).collect::<Vec<_>>();
+            for error in &errors {
+                eprintln!("{}", error);
+            }
+            if feedback.quiet {
+                result.tasks.iter().for_each(|task| println!("{}", task.id));
+                return Ok(());
+            }
+            for duplicate in &result.duplicates {
+                println!("Skipped duplicate \"{}\"", duplicate.name);
+            }
+            match dry_run {
+                true => {
+                    if !result.tasks.is_empty() {
+                        display_tasks(result.tasks.iter().collect(), &task_manager.app_data.config.workflow, false);
+                    }
+                    println!(
+                        "Would import {} task(s), skipping {} duplicate(s) and {} error(s)",
+                        result.tasks.len(),
+                        result.duplicates.len(),
+                        errors.len()
+                    );
+                }
+                false => println!(
+                    "Imported {} task(s), skipped {} duplicate(s) and {} error(s)",
+                    result.tasks.len(),
+                    result.duplicates.len(),
+                    errors.len()
+                ),
+            }
+        }
+        SubCommands::Export {
+            format,
+            date_format,
+            group_by_tag,
+        } => {
+            let config = &task_manager.app_data.config;
+            let options = ExportOptions {
+                date_format: date_format.unwrap_or_else(|| config.date_format.clone()),
+                group_by_tag,
+            };
+            let tasks = task_manager.app_data.tasks.values().collect::<Vec<_>>();
+            print!("{}", format_tasks(format, &tasks, &config.workflow, &options)?);
+        }
+        SubCommands::Backup { path } => {
+            let path = task_manager.app_data.backup(path.as_deref())?;
+            match feedback.quiet {
+                true => println!("{}", path.display()),
+                false => println!("Backup saved to {}", path.display()),
+            }
+        }
+        SubCommands::Restore { path, mode, yes } => {
+            let app_data = AppData::read_backup(&path)?;
+            let issues = diagnose(&app_data);
+            if let RestoreMode::Replace = mode {
+                let question = format!(
+                    "Replace {} task(s) with the {} task(s) of the backup?",
+                    task_manager.app_data.tasks.len(),
+                    app_data.tasks.len()
+                );
+                if !yes && !confirm(&question)? {
+                    println!("Aborted");
+                    return Ok(());
+                }
+            }
+            let backup = task_manager.app_data.backup(None)?;
+            let count = app_data.tasks.len();
+            let restored = match mode {
+                RestoreMode::Replace => {
+                    task_manager.replace_data(app_data)?;
+                    format!("Restored {} task(s)", count)
+                }
+                RestoreMode::Merge => {
+                    let ids = task_manager.merge_data(app_data)?;
+                    format!("Added {} of {} task(s)", ids.len(), count)
+                }
+            };
+            if !feedback.quiet {
+                if !issues.is_empty() {
+                    display_issues(&issues);
+                    println!("Fixed {} problem(s) found in the backup", issues.len());
+                }
+                println!("{}, previous data saved to {}", restored, backup.display());
+            }
+        }
+        SubCommands::Archive { older_than } => {
+            let older_than = older_than.as_deref().map(parse_duration).transpose()?;
+            let ids = task_manager.archive_tasks(older_than)?;
+            match feedback.quiet {
+                true => ids.iter().for_each(|id| println!("{}", id)),
+                false => println!("Archived {} task(s)", ids.len()),
+            }
+        }
+        SubCommands::Done { selection } => {
+            let Some(ids) = select_tasks(&task_manager, &selection, "Complete")? else {
+                return Ok(());
+            };
+            task_manager.mark_done(&ids)?;
+            let changes = describe_changes(&task_manager, &ids, "Completed");
+            report(&task_manager, &feedback, changes)?;
+        }
+        SubCommands::Reopen { selection } => {
+            let Some(ids) = select_tasks(&task_manager, &selection, "Reopen")? else {
+                return Ok(());
+            };
+            task_manager.reopen_tasks(&ids)?;
+            let changes = describe_changes(&task_manager, &ids, "Reopened");
+            report(&task_manager, &feedback, changes)?;
+        }
+        SubCommands::Start { selection } => {
+            let Some(ids) = select_tasks(&task_manager, &selection, "Start")? else {
+                return Ok(());
+            };
+            let started = task_manager.app_data.config.workflow.started.clone();
+            task_manager.move_tasks(&ids, &started)?;
+            let changes = describe_changes(&task_manager, &ids, "Started");
+            report(&task_manager, &feedback, changes)?;
+        }
+        SubCommands::Cancel { selection } => {
+            let Some(ids) = select_tasks(&task_manager, &selection, "Cancel")? else {
+                return Ok(());
+            };
+            let cancelled = task_manager.app_data.config.workflow.cancelled.clone();
+            task_manager.move_tasks(&ids, &cancelled)?;
+            let changes = describe_changes(&task_manager, &ids, "Cancelled");
+            report(&task_manager, &feedback, changes)?;
+        }
+        SubCommands::Move { selection, to } => {
+            let Some(ids) = select_tasks(&task_manager, &selection, "Move")? else {
+                return Ok(());
+            };
+            task_manager.move_tasks(&ids, &to)?;
+            let changes = describe_changes(&task_manager, &ids, "Moved")
+                .into_iter()
+                .map(|(id, change)| (id, format!("{} to {}", change, to)))
+                .collect();
+            report(&task_manager, &feedback, changes)?;
+        }
+        SubCommands::Note { task_id, text } => {
+            let id = parse_id(&task_id, &task_manager.app_data.tasks)?;
+            task_manager.annotate_task(id, text)?;
+            let changes = describe_changes(&task_manager, &[id], "Added a note to");
+            report(&task_manager, &feedback, changes)?;
         }
+        SubCommands::Show { task_id, output } => {
+            let id = parse_id(&task_id, &task_manager.app_data.tasks)?;
+            task_manager.check_ids(&[id])?;
+            let task = &task_manager.app_data.tasks[&id];
+            let related = task_manager.related_tasks(id);
+            match output {
+                ShowFormat::Card => display_task(task, &related, &task_manager.app_data.config.workflow),
+                ShowFormat::Json => {
+                    let details = TaskDetails {
+                        task,
+                        related: related.iter().map(|task| task.id).collect(),
+                    };
+                    println!("{}", serde_json::to_string_pretty(&details)?);
+                }
+            }
+        }
+        SubCommands::Remove { selection } => {
+            let Some(ids) = select_tasks(&task_manager, &selection, "Remove")? else {
+                return Ok(());
+            };
+            // removed tasks can't be described afterwards
+            let changes = describe_changes(&task_manager, &ids, "Removed");
+            task_manager.remove_tasks(&ids)?;
+            report(&task_manager, &feedback, changes)?;
+        }
+        SubCommands::Tags { action } => {
+            match action {
+                None => {}
+                Some(TagCommands::Rename { tag, new_name }) => {
+                    task_manager.rename_tag(&tag, &new_name)?
+                }
+                Some(TagCommands::Merge { tags, into }) => task_manager.merge_tags(&tags, &into)?,
+                Some(TagCommands::Delete { tag }) => task_manager.delete_tag(&tag)?,
+                Some(TagCommands::Describe { tag, description }) => {
+                    task_manager.describe_tag(&tag, description)?
+                }
+                Some(TagCommands::Color { tag, color }) => task_manager.color_tag(&tag, &color)?,
+            }
+            display_tags(task_manager.tag_summaries())
+        }
+        SubCommands::Reset {
+            done_only,
+            tag,
+            before,
+            yes,
+        } => {
+            let scope = ResetScope {
+                done_only,
+                tag,
+                before,
+            };
+            let ids = task_manager.tasks_to_reset(&scope)?;
+            if ids.is_empty() {
+                println!("No tasks to remove");
+                return Ok(());
+            }
+            if !yes {
+                let question = match scope.is_empty() {
+                    true => format!("Remove all {} task(s)?", ids.len()),
+                    false => {
+                        let tasks = ids.iter().map(|id| &task_manager.app_data.tasks[id]).collect();
+                        display_tasks(tasks, &task_manager.app_data.config.workflow, false);
+                        format!("Remove {} task(s)?", ids.len())
+                    }
+                };
+                if !confirm(&question)? {
+                    println!("Aborted");
+                    return Ok(());
+                }
+            }
+            let backup = task_manager.app_data.backup(None)?;
+            match scope.is_empty() {
+                true => task_manager.reset_tasks()?,
+                false => task_manager.remove_tasks(&ids)?,
+            }
+            match feedback.quiet {
+                true => ids.iter().for_each(|id| println!("{}", id)),
+                false => println!("Removed {} task(s), backup saved to {}", ids.len(), backup.display()),
+            }
+        }
+        SubCommands::Tui => {
+            tui::run(task_manager)?;
+        }
+        SubCommands::Renumber { all } => {
+            let moves = task_manager.renumber_tasks(all)?;
+            if moves.is_empty() && !feedback.quiet {
+                println!("Task ids are already compact");
+            }
+            let changes = moves
+                .iter()
+                .map(|(old, new)| {
+                    let name = &task_manager.app_data.tasks[new].name;
+                    (*new, format!("Renumbered task {} \"{}\" to {}", old, name, new))
+                })
+                .collect();
+            report(&task_manager, &feedback, changes)?;
+        }
+        SubCommands::Doctor { fix } => {
+            let issues = diagnose(&task_manager.app_data);
+            display_issues(&issues);
+            if !issues.is_empty() {
+                match fix {
+                    true => {
+                        task_manager.repair()?;
+                        println!("Fixed {} problem(s)", issues.len());
+                    }
+                    false => {
+                        return Err(format!(
+                            "Found {} problem(s), run `todo doctor --fix` to repair them",
+                            issues.len()
+                        )
+                        .into())
+                    }
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Resolve the ids of selected tasks, showing them and asking for confirmation
+/// when more than one task or a filter is involved. Returns `None` if the user
+/// declined.
+fn select_tasks(
+    task_manager: &TaskManager,
+    selection: &Selection,
+    action: &str,
+) -> TodoResult<Option<Vec<usize>>> {
+    let ids = match (&selection.task_ids, &selection.filter) {
+        (Some(ids), _) => parse_ids(ids, &task_manager.app_data.tasks)?,
+        (None, Some(filter)) => {
+            let query = TaskQuery::parse(filter, &task_manager.app_data.config.date_format)?;
+            task_manager.find_tasks(&query)
+        }
+        (None, None) => return Err("No task id given".into()),
+    };
+    if ids.is_empty() {
+        return Err("No tasks match given filter".into());
+    }
+    task_manager.check_ids(&ids)?;
+    if (ids.len() > 1 || selection.filter.is_some()) && !selection.yes {
+        display_tasks(
+            ids.iter().map(|id| &task_manager.app_data.tasks[id]).collect(),
+            &task_manager.app_data.config.workflow,
+            false,
+        );
+        if !confirm(&format!("{} {} task(s)?", action, ids.len()))? {
+            println!("Aborted");
+            return Ok(None);
+        }
+    }
+    Ok(Some(ids))
+}
+
+fn confirm(question: &str) -> TodoResult<bool> {
+    print!("{} [y/N] ", question);
+    io::stdout().flush()?;
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer)?;
+    Ok(matches!(answer.trim().to_lowercase().as_str(), "y" | "yes"))
+}
+
+/// How to report tasks changed by a command
+#[derive(Clone, Copy)]
+struct Feedback {
+    quiet: bool,
+    show_list: bool,
+    display_ids: bool,
+}
+
+/// One confirmation line per given task, e.g. `Added task 42 "Buy milk"`
+fn describe_changes(task_manager: &TaskManager, ids: &[usize], verb: &str) -> Vec<(usize, String)> {
+    ids.iter()
+        .map(|id| {
+            let name = &task_manager.app_data.tasks[id].name;
+            (*id, format!("{} task {} \"{}\"", verb, id, name))
+        })
+        .collect()
+}
+
+/// Print the ids of changed tasks when quiet, the whole list when asked for,
+/// and a confirmation per task otherwise
+fn report(task_manager: &TaskManager, feedback: &Feedback, changes: Vec<(usize, String)>) -> TodoResult<()> {
+    if feedback.quiet {
+        changes.iter().for_each(|(id, _)| println!("{}", id));
+    } else if feedback.show_list {
+        let tasks: Vec<&Task> = task_manager
+            .app_data
+            .tasks
+            .values()
+            .collect::<Vec<_>>();
+        print_tasks(&tasks, &task_manager.app_data.config.workflow, feedback.display_ids)?;
+    } else {
+        changes.iter().for_each(|(_, change)| println!("{}", change));
     }
     Ok(())
 }
 
-fn parse_id(id: &str) -> TodoResult<usize> {
-    id.parse::<usize>()
-        .map_err(|_| format!("task with id {} not found", id).into())
+/// Print a listing as a table, or only the ids when quiet
+fn print_listing(task_manager: &TaskManager, feedback: &Feedback, tasks: Vec<&Task>) -> TodoResult<()> {
+    match feedback.quiet {
+        true => sort_tasks(tasks).iter().rev().for_each(|task| println!("{}", task.id)),
+        false => print_tasks(&tasks, &task_manager.app_data.config.workflow, feedback.display_ids)?,
+    }
+    Ok(())
 }
 
-fn print_tasks(tasks: &Vec<&Task>) {
+/// Print tasks as a table, remembering their display ids when shown
+fn print_tasks(tasks: &Vec<&Task>, workflow: &Workflow, display_ids: bool) -> TodoResult<()> {
     let mut tasks = sort_tasks(tasks.to_owned());
     tasks.reverse();
     match tasks.is_empty() {
         true => println!("No tasks found"),
-        false => display_tasks(tasks),
+        false => {
+            if display_ids {
+                save_display_ids(&tasks)?;
+            }
+            display_tasks(tasks, workflow, display_ids)
+        }
     }
+    Ok(())
 }
 
 fn sort_tasks(tasks: Vec<&Task>) -> Vec<&Task> {
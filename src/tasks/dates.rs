@@ -0,0 +1,149 @@
+use chrono::{Duration, Months, NaiveDate, NaiveDateTime};
+
+use crate::app::DEFAULT_TIMESTAMP_FORMAT;
+use crate::TodoResult;
+
+/// Parse a stored timestamp, see `timestamp`
+pub fn parse_timestamp(timestamp: &str) -> Option<NaiveDateTime> {
+    NaiveDateTime::parse_from_str(timestamp, DEFAULT_TIMESTAMP_FORMAT).ok()
+}
+
+/// Parse a duration given as a number of days or weeks, e.g. `14d` or `2w`
+pub fn parse_duration(duration: &str) -> TodoResult<Duration> {
+    let duration = duration.trim().to_lowercase();
+    let invalid = || format!("Invalid duration {}, expected e.g. 3d or 2w", duration);
+    let (count, unit) = match duration.char_indices().last() {
+        Some((index, unit)) => (&duration[..index], unit),
+        None => return Err(invalid().into()),
+    };
+    // a sign would let `+3d` through and negative counts point into the future
+    if !count.chars().all(|c| c.is_ascii_digit()) {
+        return Err(invalid().into());
+    }
+    let count = count.parse::<i64>().map_err(|_| invalid())?;
+    let duration = match unit {
+        'd' => Duration::try_days(count),
+        'w' => Duration::try_weeks(count),
+        _ => None,
+    };
+    duration.ok_or_else(|| invalid().into())
+}
+
+/// Parse the start of a date range relative to `today`: `today`, `yesterday`,
+/// `last-week`, `last-month`, a duration back from today (e.g. `3d`) or a date
+/// in given format
+pub fn parse_since(since: &str, date_format: &str, today: NaiveDate) -> TodoResult<NaiveDate> {
+    match since.trim().to_lowercase().as_str() {
+        "today" => Ok(today),
+        "yesterday" => Ok(today - Duration::days(1)),
+        "last-week" => Ok(today - Duration::weeks(1)),
+        "last-month" => Ok(today.checked_sub_months(Months::new(1)).unwrap_or(today)),
+        _ => NaiveDate::parse_from_str(since, date_format)
+            .ok()
+            .or_else(|| today.checked_sub_signed(parse_duration(since).ok()?))
+            .ok_or_else(|| {
+                format!(
+                    "Invalid date {}, expected today, yesterday, last-week, last-month, a duration like 3d or a date in format: {}",
+                    since, date_format
+                )
+                .into()
+            }),
+    }
+}
+
+/// Describe how far `then` lies from `now`, e.g. `3 hours ago` or `in 2 weeks`,
+/// a year is counted as 52 weeks
+pub fn relative_time(then: NaiveDateTime, now: NaiveDateTime) -> String {
+    let seconds = (then - now).num_seconds();
+    if seconds.abs() < 60 {
+        return "just now".to_string();
+    }
+    describe_offset(seconds / 60, &[(60, "minute"), (24, "hour"), (7, "day"), (52, "week"), (i64::MAX, "year")])
+}
+
+/// Describe how far `date` lies from `today`, e.g. `tomorrow` or `5 days ago`
+pub fn relative_date(date: NaiveDate, today: NaiveDate) -> String {
+    match (date - today).num_days() {
+        0 => "today".to_string(),
+        1 => "tomorrow".to_string(),
+        -1 => "yesterday".to_string(),
+        days => describe_offset(days, &[(7, "day"), (52, "week"), (i64::MAX, "year")]),
+    }
+}
+
+/// Express `count` of the first unit in the largest unit it fills, each unit
+/// holding the given number of the previous one
+fn describe_offset(count: i64, units: &[(i64, &str)]) -> String {
+    let mut amount = count.abs();
+    let mut unit = units[0].1;
+    for window in units.windows(2) {
+        let (size, _) = window[0];
+        if amount < size {
+            break;
+        }
+        amount /= size;
+        unit = window[1].1;
+    }
+    let plural = if amount == 1 { "" } else { "s" };
+    match count < 0 {
+        true => format!("{} {}{} ago", amount, unit, plural),
+        false => format!("in {} {}{}", amount, unit, plural),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    #[test]
+    fn parses_days_and_weeks() {
+        assert_eq!(parse_duration("14d").unwrap(), Duration::days(14));
+        assert_eq!(parse_duration(" 2W ").unwrap(), Duration::weeks(2));
+        assert_eq!(parse_duration("0d").unwrap(), Duration::zero());
+    }
+
+    #[test]
+    fn rejects_malformed_durations() {
+        for duration in ["", "d", "3", "3x", "3é", "é", "-3d", "+3d", "3.5d", "3 d"] {
+            assert!(parse_duration(duration).is_err(), "{:?} was accepted", duration);
+        }
+    }
+
+    #[test]
+    fn rejects_out_of_range_durations() {
+        assert!(parse_duration("9999999999999999d").is_err());
+        assert!(parse_duration("9999999999999999999999d").is_err());
+        assert!(parse_duration("999999999999999w").is_err());
+    }
+
+    #[test]
+    fn parses_since() {
+        let today = date(2024, 3, 15);
+        assert_eq!(parse_since("today", "%d-%m-%Y", today).unwrap(), today);
+        assert_eq!(parse_since("yesterday", "%d-%m-%Y", today).unwrap(), date(2024, 3, 14));
+        assert_eq!(parse_since("last-week", "%d-%m-%Y", today).unwrap(), date(2024, 3, 8));
+        assert_eq!(parse_since("last-month", "%d-%m-%Y", today).unwrap(), date(2024, 2, 15));
+        assert_eq!(parse_since("3d", "%d-%m-%Y", today).unwrap(), date(2024, 3, 12));
+        assert_eq!(parse_since("01-02-2024", "%d-%m-%Y", today).unwrap(), date(2024, 2, 1));
+    }
+
+    #[test]
+    fn rejects_since_before_the_earliest_date() {
+        let today = date(2024, 3, 15);
+        assert!(parse_since("99999999d", "%d-%m-%Y", today).is_err());
+        assert!(parse_since("3é", "%d-%m-%Y", today).is_err());
+    }
+
+    #[test]
+    fn describes_relative_dates() {
+        let today = date(2024, 3, 15);
+        assert_eq!(relative_date(today, today), "today");
+        assert_eq!(relative_date(date(2024, 3, 16), today), "tomorrow");
+        assert_eq!(relative_date(date(2024, 3, 10), today), "5 days ago");
+        assert_eq!(relative_date(date(2024, 3, 29), today), "in 2 weeks");
+    }
+}
@@ -1,28 +1,41 @@
 use colored::*;
-use prettytable::{row, Table};
+use prettytable::{row, Cell, Row, Table};
 
-use crate::Task;
+use crate::{parse_timestamp, relative_date, relative_time, Issue, Priority, TagSummary, Task, Workflow};
 
-/// Display given tasks in tabular format
-pub fn display_tasks(tasks: Vec<&Task>) {
+/// Display given tasks in tabular format, numbering them `@1`, `@2`, ... in a
+/// first column when `display_ids` is set
+pub fn display_tasks(tasks: Vec<&Task>, workflow: &Workflow, display_ids: bool) {
     print!("\n{}\t", "Legend:".bold().bright_cyan());
-    print!("{}\t", "Priority task".underline().yellow());
+    print!("{}\t", "Critical priority".underline().magenta());
+    print!("{}\t", "High priority".underline().yellow());
     print!("{}\t", "Due today".underline().red());
-    println!("{}\t", "Completed".underline().green());
-    println!("");
+    print!("{}\t", "Completed".underline().green());
+    println!("{}\t", "Cancelled".underline().bright_black());
+    println!();
     let mut table = Table::new();
-    table.add_row(row![
+    let mut header = row![
         "ID".to_string().bold().bright_blue(),
         "Title".to_string().bold().bright_blue(),
+        "Priority".to_string().bold().bright_blue(),
         "Due Date".to_string().bold().bright_blue(),
         "tags".to_string().bold().bright_blue(),
-        "Completed".to_string().bold().bright_blue(),
+        "State".to_string().bold().bright_blue(),
+        "Completed On".to_string().bold().bright_blue(),
         "Created On".to_string().bold().bright_blue(),
         "Last Updated".to_string().bold().bright_blue(),
-    ]);
-    for task in tasks {
+    ];
+    if display_ids {
+        header.insert_cell(0, Cell::new(&"#".bold().bright_blue().to_string()));
+    }
+    table.add_row(header);
+    for (number, task) in tasks.into_iter().enumerate() {
         let id = task.id.to_string();
         let description = task.name.to_string();
+        let priority = match task.priority {
+            Priority::None => "-".to_string(),
+            priority => priority.to_string(),
+        };
         let due_date = match &task.due_date {
             Some(date) => date.to_string(),
             None => "-".to_string(),
@@ -31,69 +44,181 @@ pub fn display_tasks(tasks: Vec<&Task>) {
             Some(date) => date.to_string(),
             None => "-".to_string(),
         };
-        let completed = if task.done { "Yes" } else { "No" };
+        let state = task.state.to_string();
+        let completed_at = match &task.completed_at {
+            Some(date) => date.to_string(),
+            None => "-".to_string(),
+        };
         let tags = match &task.tags {
             Some(tags) => tags.join(", "),
             None => "-".to_string(),
         };
         let created_at = task.created_at.to_owned();
-        let mut row = row![
+        let mut cells = vec![
             id,
             description,
+            priority,
             due_date,
             tags,
-            completed,
+            state,
+            completed_at,
             created_at,
-            last_updated
+            last_updated,
         ];
-        if task.done {
-            row = row![
-                id.green(),
-                description.green(),
-                due_date.green(),
-                tags.green(),
-                completed.green(),
-                created_at.green(),
-                last_updated.green(),
-            ];
+        if display_ids {
+            cells.insert(0, format!("@{}", number + 1));
+        }
+        // completion takes precedence over being due today, which takes precedence over priority
+        let color = if task.done {
+            Some(Color::Green)
+        } else if task.state == workflow.cancelled {
+            Some(Color::BrightBlack)
+        } else if task.is_due_today() {
+            Some(Color::Red)
         } else {
-            if task.priority {
-                row = row![
-                    id.yellow(),
-                    description.yellow(),
-                    due_date.yellow(),
-                    tags.yellow(),
-                    completed.yellow(),
-                    created_at.yellow(),
-                    last_updated.yellow(),
-                ];
+            match task.priority {
+                Priority::Critical => Some(Color::Magenta),
+                Priority::High => Some(Color::Yellow),
+                _ => None,
             }
-            if task.is_due_today() {
-                row = row![
-                    id.red(),
-                    description.red(),
-                    due_date.red(),
-                    tags.red(),
-                    completed.red(),
-                    created_at.red(),
-                    last_updated.red(),
-                ];
+        };
+        let row = cells
+            .iter()
+            .map(|cell| match color {
+                Some(color) => Cell::new(&cell.color(color).to_string()),
+                None => Cell::new(cell),
+            })
+            .collect::<Vec<_>>();
+        table.add_row(Row::new(row));
+    }
+    table.printstd();
+}
+
+/// Display every field of a single task as a card, with dates shown both
+/// relative to now and as stored, followed by its notes, history and the
+/// tasks sharing a tag with it
+pub fn display_task(task: &Task, related: &[&Task], workflow: &Workflow) {
+    let now = chrono::Local::now().naive_local();
+    let timestamp = |value: &str| match parse_timestamp(value) {
+        Some(at) => format!("{} ({})", relative_time(at, now), value),
+        None => value.to_string(),
+    };
+    let due_date = match task.get_parsed_due_date() {
+        Some(date) => format!(
+            "{} ({})",
+            relative_date(date, now.date()),
+            task.due_date.as_deref().unwrap_or_default()
+        ),
+        None => "-".to_string(),
+    };
+    let state = match workflow.is_open(&task.state) {
+        true => task.state.normal(),
+        false if task.done => task.state.green(),
+        false => task.state.bright_black(),
+    };
+    let fields = [
+        ("UUID", task.uuid.clone()),
+        ("Priority", task.priority.to_string()),
+        ("State", state.to_string()),
+        ("Due", due_date),
+        ("Tags", task.tags.as_ref().map_or("-".to_string(), |tags| tags.join(", "))),
+        ("Created", timestamp(&task.created_at)),
+        ("Updated", task.updated_at.as_deref().map_or("-".to_string(), timestamp)),
+        ("Completed", task.completed_at.as_deref().map_or("-".to_string(), timestamp)),
+    ];
+    let title = format!("#{} {}", task.id, task.name);
+    let rule = "─".repeat(title.chars().count().max(40));
+    println!();
+    println!("{}", title.bold());
+    println!("{}", rule.bright_black());
+    for (label, value) in fields {
+        println!("{:>10}  {}", label.bright_blue(), value);
+    }
+    if let Some(notes) = &task.notes {
+        println!("\n{}", "Notes".bold().bright_cyan());
+        println!("{}", notes);
+    }
+    if !task.annotations.is_empty() {
+        println!("\n{}", "Annotations".bold().bright_cyan());
+        for annotation in &task.annotations {
+            let mut lines = annotation.text.lines();
+            println!("{}  {}", annotation.at.bright_black(), lines.next().unwrap_or_default());
+            // continuation lines line up with the first one
+            for line in lines {
+                println!("{}  {}", " ".repeat(annotation.at.len()), line);
             }
         }
-        table.add_row(row);
     }
-    table.printstd();
+    if !task.history.is_empty() {
+        println!("\n{}", "History".bold().bright_cyan());
+        for change in &task.history {
+            println!("{}  {} → {}", change.at.bright_black(), change.from, change.to);
+        }
+    }
+    if !related.is_empty() {
+        println!("\n{}", "Related".bold().bright_cyan());
+        for other in related {
+            let shared = other
+                .tags
+                .iter()
+                .flatten()
+                .filter(|tag| {
+                    task.tags
+                        .iter()
+                        .flatten()
+                        .any(|t| t.to_lowercase() == tag.to_lowercase())
+                })
+                .cloned()
+                .collect::<Vec<_>>();
+            println!(
+                "{:>4}  {} {}  {}",
+                format!("#{}", other.id),
+                other.name,
+                format!("[{}]", other.state).bright_black(),
+                shared.join(", ").bright_black()
+            );
+        }
+    }
+    println!("{}", rule.bright_black());
 }
 
-pub fn display_tags(tags: Vec<String>) {
+/// Display tags with their descriptions and task counts
+pub fn display_tags(tags: Vec<TagSummary>) {
+    if tags.is_empty() {
+        println!("No tags found");
+        return;
+    }
     let mut table = Table::new();
     table.add_row(row![
         "Tags".to_string().bold().bright_blue(),
+        "Open".to_string().bold().bright_blue(),
+        "Done".to_string().bold().bright_blue(),
+        "Description".to_string().bold().bright_blue(),
     ]);
     tags.iter().for_each(|tag| {
+        // nested tags are indented below their parent with only the last segment shown
+        let label = tag.name.rsplit('/').next().unwrap_or_default();
+        let label = format!("{}{}", "  ".repeat(tag.depth), label);
+        let name = match &tag.info.color {
+            Some(color) => label.color(color.as_str()),
+            None => label.normal(),
+        };
         table.add_row(row![
-            tag,
+            name,
+            tag.open,
+            tag.done,
+            tag.info.description.as_deref().unwrap_or("-"),
         ]);
     });
     table.printstd();
 }
+
+/// Display problems found in app data
+pub fn display_issues(issues: &[Issue]) {
+    match issues.is_empty() {
+        true => println!("{}", "No problems found".green()),
+        false => issues
+            .iter()
+            .for_each(|issue| println!("{} {}", "-".red(), issue)),
+    }
+}
@@ -0,0 +1,181 @@
+use chrono::NaiveDate;
+use std::collections::HashSet;
+use std::fmt;
+
+use crate::app::DEFAULT_DATE_FORMAT;
+use crate::{parse_duration, AppData};
+
+/// An inconsistency in stored app data
+#[derive(Debug, PartialEq)]
+pub enum Issue {
+    TaskIdMismatch { key: usize, id: usize },
+    DuplicateUuid { id: usize, uuid: String },
+    NextIdTooLow { next_id: usize, max_id: usize },
+    InvalidDueDate { id: usize, due_date: String },
+    UnknownState { id: usize, state: String },
+    DoneMismatch { id: usize, state: String, done: bool },
+    InvalidArchiveAfter { archive_after: String },
+    InvalidWorkflow { error: String },
+    TagKeyNotLowercase { tag: String },
+    MissingTagEntry { tag: String, id: usize },
+    StaleTagEntry { tag: String, id: usize },
+    DuplicateTagEntry { tag: String, id: usize },
+}
+
+impl fmt::Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Issue::TaskIdMismatch { key, id } => {
+                write!(f, "task stored under id {} has id {}", key, id)
+            }
+            Issue::DuplicateUuid { id, uuid } => {
+                write!(f, "task {} has UUID {} of another task", id, uuid)
+            }
+            Issue::NextIdTooLow { next_id, max_id } => write!(
+                f,
+                "next id {} is not greater than the highest task id {}",
+                next_id, max_id
+            ),
+            Issue::InvalidDueDate { id, due_date } => {
+                write!(f, "task {} has an unreadable due date {:?}", id, due_date)
+            }
+            Issue::UnknownState { id, state } => {
+                write!(f, "task {} is in state {:?} unknown to the workflow", id, state)
+            }
+            Issue::DoneMismatch { id, state, done } => write!(
+                f,
+                "task {} is in state {:?} but is marked as {}",
+                id,
+                state,
+                if *done { "done" } else { "not done" }
+            ),
+            Issue::InvalidArchiveAfter { archive_after } => write!(
+                f,
+                "config archive_after {:?} is not a duration like 30d or 4w",
+                archive_after
+            ),
+            Issue::InvalidWorkflow { error } => {
+                write!(f, "workflow in config is invalid, {}, fix it by hand", error)
+            }
+            Issue::TagKeyNotLowercase { tag } => {
+                write!(f, "tag index entry {:?} is not lowercase", tag)
+            }
+            Issue::MissingTagEntry { tag, id } => write!(
+                f,
+                "task {} has tag {:?} but is missing from the tag index",
+                id, tag
+            ),
+            Issue::StaleTagEntry { tag, id } => write!(
+                f,
+                "tag index lists task {} under {:?} but the task doesn't have that tag",
+                id, tag
+            ),
+            Issue::DuplicateTagEntry { tag, id } => {
+                write!(f, "task {} is listed more than once under tag {:?}", id, tag)
+            }
+        }
+    }
+}
+
+/// Check tasks, the tag index, `next_id` and the workflow against each other
+pub fn diagnose(app_data: &AppData) -> Vec<Issue> {
+    let mut issues = vec![];
+    let mut uuids = HashSet::new();
+    for (key, task) in &app_data.tasks {
+        if *key != task.id {
+            issues.push(Issue::TaskIdMismatch { key: *key, id: task.id });
+        }
+        if !uuids.insert(&task.uuid) {
+            issues.push(Issue::DuplicateUuid {
+                id: *key,
+                uuid: task.uuid.clone(),
+            });
+        }
+    }
+    if let Some(max_id) = app_data.tasks.keys().max() {
+        if app_data.next_id <= *max_id {
+            issues.push(Issue::NextIdTooLow {
+                next_id: app_data.next_id,
+                max_id: *max_id,
+            });
+        }
+    }
+    let workflow = &app_data.config.workflow;
+    // task states can't be checked against a broken workflow
+    let valid_workflow = match workflow.validate() {
+        Ok(()) => true,
+        Err(e) => {
+            issues.push(Issue::InvalidWorkflow { error: e.to_string() });
+            false
+        }
+    };
+    for task in app_data.tasks.values() {
+        if valid_workflow {
+            if workflow.check_state(&task.state).is_err() {
+                issues.push(Issue::UnknownState {
+                    id: task.id,
+                    state: task.state.clone(),
+                });
+            } else if task.done != (task.state == workflow.done) {
+                issues.push(Issue::DoneMismatch {
+                    id: task.id,
+                    state: task.state.clone(),
+                    done: task.done,
+                });
+            }
+        }
+        if let Some(due_date) = &task.due_date {
+            if NaiveDate::parse_from_str(due_date, DEFAULT_DATE_FORMAT).is_err() {
+                issues.push(Issue::InvalidDueDate {
+                    id: task.id,
+                    due_date: due_date.clone(),
+                });
+            }
+        }
+    }
+    if let Some(archive_after) = &app_data.config.archive_after {
+        if parse_duration(archive_after).is_err() {
+            issues.push(Issue::InvalidArchiveAfter {
+                archive_after: archive_after.clone(),
+            });
+        }
+    }
+
+    let mut tags = app_data.tags.keys().collect::<Vec<_>>();
+    tags.sort();
+    for tag in tags {
+        if *tag != tag.to_lowercase() {
+            issues.push(Issue::TagKeyNotLowercase { tag: tag.clone() });
+        }
+        let ids = &app_data.tags[tag];
+        for (i, id) in ids.iter().enumerate() {
+            if ids[..i].contains(id) {
+                if ids[..i].iter().filter(|x| *x == id).count() == 1 {
+                    issues.push(Issue::DuplicateTagEntry { tag: tag.clone(), id: *id });
+                }
+                continue;
+            }
+            let has_tag = app_data.tasks.get(id).is_some_and(|task| {
+                task.tags
+                    .iter()
+                    .flatten()
+                    .any(|t| t.to_lowercase() == tag.to_lowercase())
+            });
+            if !has_tag {
+                issues.push(Issue::StaleTagEntry { tag: tag.clone(), id: *id });
+            }
+        }
+    }
+    for task in app_data.tasks.values() {
+        for tag in task.tags.iter().flatten() {
+            let indexed = app_data
+                .tags
+                .get(&tag.to_lowercase())
+                .is_some_and(|ids| ids.contains(&task.id));
+            if !indexed {
+                issues.push(Issue::MissingTagEntry { tag: tag.clone(), id: task.id });
+            }
+        }
+    }
+    issues
+}
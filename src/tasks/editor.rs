@@ -0,0 +1,167 @@
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::fs::{self, File, OpenOptions};
+use std::io::{ErrorKind, Write};
+#[cfg(unix)]
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use uuid::Uuid;
+
+use crate::{Priority, TaskManager, TaskUpdate, TodoResult};
+
+/// Task fields as presented in the editor
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TaskDocument {
+    name: String,
+    #[serde(default)]
+    priority: Priority,
+    due: Option<String>,
+    #[serde(default)]
+    tags: Vec<String>,
+    #[serde(default)]
+    done: bool,
+    state: Option<String>,
+    notes: Option<String>,
+}
+
+const HEADER: &str = "# Edit the task below, save and close the editor to apply.
+# Remove `due` or `notes` to clear them, empty the file to cancel.
+# A changed `state` takes precedence over `done`.
+";
+const ERROR_PREFIX: &str = "# error: ";
+
+/// Open task with given id in `$VISUAL`/`$EDITOR` and apply the saved document,
+/// re-opening the editor with the error shown until it's valid
+pub fn edit_in_editor(task_manager: &mut TaskManager, id: usize) -> TodoResult<()> {
+    let task = task_manager
+        .app_data
+        .tasks
+        .get(&id)
+        .ok_or_else(|| format!("task with id {} not found", id))?;
+    let config = &task_manager.app_data.config;
+    let date_format = config.date_format.clone();
+    let states = config.workflow.states.join(", ");
+    let document = TaskDocument {
+        name: task.name.clone(),
+        priority: task.priority,
+        due: task
+            .get_parsed_due_date()
+            .map(|date| date.format(&date_format).to_string()),
+        tags: task.tags.clone().unwrap_or_default(),
+        done: task.done,
+        state: Some(task.state.clone()),
+        notes: task.notes.clone(),
+    };
+    let (path, file) = create_temp_file(id)?;
+    let contents = format!(
+        "{}# Due date format: {}\n# States: {}\n\n{}",
+        HEADER,
+        date_format,
+        states,
+        toml::to_string(&document)?
+    );
+    let result = edit_until_valid(task_manager, id, &path, file, contents);
+    // the file may not exist if it couldn't be written in the first place
+    let _ = fs::remove_file(&path);
+    result
+}
+
+/// Create a new file only the user can read in the temp directory, under a
+/// name no one can guess and claim first
+fn create_temp_file(id: usize) -> TodoResult<(PathBuf, File)> {
+    for _ in 0..10 {
+        let path = env::temp_dir().join(format!("todo-{}-{}.toml", id, Uuid::new_v4().simple()));
+        let mut options = OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        options.mode(0o600);
+        match options.open(&path) {
+            Ok(file) => return Ok((path, file)),
+            Err(e) if e.kind() == ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(format!("Failed to create {}: {}", path.display(), e).into()),
+        }
+    }
+    Err("Failed to create a temporary file for the editor".into())
+}
+
+fn edit_until_valid(
+    task_manager: &mut TaskManager,
+    id: usize,
+    path: &Path,
+    file: File,
+    mut contents: String,
+) -> TodoResult<()> {
+    let mut file = Some(file);
+    loop {
+        // later rounds rewrite the file the editor saved, without creating one
+        let mut writer = match file.take() {
+            Some(file) => file,
+            None => OpenOptions::new().write(true).truncate(true).open(path)?,
+        };
+        writer.write_all(contents.as_bytes())?;
+        drop(writer);
+        open_editor(path)?;
+        contents = strip_error(&fs::read_to_string(path)?);
+        let body = contents
+            .lines()
+            .filter(|line| !line.trim_start().starts_with('#'))
+            .collect::<String>();
+        if body.trim().is_empty() {
+            return Err("Edit cancelled".into());
+        }
+        let error = match toml::from_str::<TaskDocument>(&contents) {
+            Ok(document) => match task_manager.edit_task(id, document.into()) {
+                Ok(()) => return Ok(()),
+                Err(e) => e.to_string(),
+            },
+            Err(e) => e.to_string(),
+        };
+        let error = error
+            .lines()
+            .map(|line| format!("{}{}\n", ERROR_PREFIX, line))
+            .collect::<String>();
+        contents = format!("{}{}", error, contents);
+    }
+}
+
+fn strip_error(contents: &str) -> String {
+    contents
+        .lines()
+        .filter(|line| !line.starts_with(ERROR_PREFIX))
+        .map(|line| format!("{}\n", line))
+        .collect()
+}
+
+fn open_editor(path: &Path) -> TodoResult<()> {
+    let editor = env::var("VISUAL")
+        .or_else(|_| env::var("EDITOR"))
+        .unwrap_or_else(|_| "vi".to_string());
+    let mut parts = editor.split_whitespace();
+    let program = parts.next().ok_or("No editor configured, set $EDITOR")?;
+    let status = Command::new(program)
+        .args(parts)
+        .arg(path)
+        .status()
+        .map_err(|e| format!("Failed to launch editor {}: {}", program, e))?;
+    match status.success() {
+        true => Ok(()),
+        false => Err(format!("Editor {} exited with {}", program, status).into()),
+    }
+}
+
+impl From<TaskDocument> for TaskUpdate {
+    fn from(document: TaskDocument) -> Self {
+        TaskUpdate {
+            name: Some(document.name),
+            priority: Some(document.priority),
+            due_date: Some(document.due.unwrap_or_default()),
+            tags: Some(document.tags),
+            done: Some(document.done),
+            state: document.state,
+            notes: Some(document.notes.unwrap_or_default()),
+            ..Default::default()
+        }
+    }
+}
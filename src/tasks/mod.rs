@@ -1,7 +1,17 @@
 pub mod task_manager;
 pub mod task;
+pub mod dates;
 pub mod display;
+pub mod doctor;
+pub mod editor;
+pub mod priority;
+pub mod selector;
 
 pub use task_manager::*;
 pub use task::*;
-pub use display::*;
\ No newline at end of file
+pub use dates::*;
+pub use display::*;
+pub use doctor::*;
+pub use editor::*;
+pub use priority::*;
+pub use selector::*;
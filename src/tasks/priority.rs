@@ -0,0 +1,152 @@
+use serde::{de, Deserialize, Deserializer, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// Ordered priority levels of a task
+#[derive(Serialize, Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum Priority {
+    #[default]
+    None,
+    Low,
+    Medium,
+    High,
+    Critical,
+}
+
+impl Priority {
+    pub const ALL: [Priority; 5] = [
+        Priority::None,
+        Priority::Low,
+        Priority::Medium,
+        Priority::High,
+        Priority::Critical,
+    ];
+
+    /// The next higher level, wrapping around to none after critical
+    pub fn next(self) -> Self {
+        Priority::ALL[(self as usize + 1) % Priority::ALL.len()]
+    }
+}
+
+impl fmt::Display for Priority {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Priority::None => "none",
+            Priority::Low => "low",
+            Priority::Medium => "medium",
+            Priority::High => "high",
+            Priority::Critical => "critical",
+        };
+        f.pad(name)
+    }
+}
+
+impl FromStr for Priority {
+    type Err = String;
+
+    /// Parse a level name or its P0-P4 alias, `true` and `false` are kept from
+    /// the time priority was a flag
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "none" | "p4" | "false" => Ok(Priority::None),
+            "low" | "p3" => Ok(Priority::Low),
+            "medium" | "p2" => Ok(Priority::Medium),
+            "high" | "p1" | "true" => Ok(Priority::High),
+            "critical" | "p0" => Ok(Priority::Critical),
+            _ => Err(format!(
+                "Invalid priority {}, expected one of none, low, medium, high, critical or P0-P4",
+                s
+            )),
+        }
+    }
+}
+
+/// Stored priorities are either a level or a flag written by older versions
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum StoredPriority {
+    Flag(bool),
+    Level(String),
+}
+
+impl<'de> Deserialize<'de> for Priority {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        match StoredPriority::deserialize(deserializer)? {
+            StoredPriority::Flag(true) => Ok(Priority::High),
+            StoredPriority::Flag(false) => Ok(Priority::None),
+            StoredPriority::Level(level) => level.parse().map_err(de::Error::custom),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Comparison {
+    Equal,
+    Greater,
+    GreaterOrEqual,
+    Less,
+    LessOrEqual,
+}
+
+/// A comparison against a priority level, e.g. `>=high`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PriorityFilter {
+    comparison: Comparison,
+    level: Priority,
+}
+
+impl PriorityFilter {
+    pub fn at_least(level: Priority) -> Self {
+        PriorityFilter {
+            comparison: Comparison::GreaterOrEqual,
+            level,
+        }
+    }
+
+    pub fn matches(&self, priority: Priority) -> bool {
+        match self.comparison {
+            Comparison::Equal => priority == self.level,
+            Comparison::Greater => priority > self.level,
+            Comparison::GreaterOrEqual => priority >= self.level,
+            Comparison::Less => priority < self.level,
+            Comparison::LessOrEqual => priority <= self.level,
+        }
+    }
+}
+
+impl fmt::Display for PriorityFilter {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let comparison = match self.comparison {
+            Comparison::Equal => "",
+            Comparison::Greater => ">",
+            Comparison::GreaterOrEqual => ">=",
+            Comparison::Less => "<",
+            Comparison::LessOrEqual => "<=",
+        };
+        write!(f, "{}{}", comparison, self.level)
+    }
+}
+
+impl FromStr for PriorityFilter {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        // two character operators have to be tried before their prefixes
+        let (comparison, level) = [
+            (">=", Comparison::GreaterOrEqual),
+            ("<=", Comparison::LessOrEqual),
+            (">", Comparison::Greater),
+            ("<", Comparison::Less),
+            ("=", Comparison::Equal),
+        ]
+        .iter()
+        .find_map(|(op, comparison)| s.strip_prefix(op).map(|level| (*comparison, level)))
+        .unwrap_or((Comparison::Equal, s));
+        Ok(PriorityFilter {
+            comparison,
+            level: level.parse()?,
+        })
+    }
+}
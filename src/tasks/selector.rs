@@ -0,0 +1,201 @@
+use chrono::NaiveDate;
+use std::collections::BTreeMap;
+
+use crate::app::read_display_ids;
+use crate::{PriorityFilter, Task, TodoResult};
+
+/// Parse a single task id, or resolve a display id like `@3` from the last
+/// listing or the unique prefix of a task UUID among given tasks. Prefixes
+/// made of digits only are read as ids.
+pub fn parse_id(id: &str, tasks: &BTreeMap<usize, Task>) -> TodoResult<usize> {
+    let id = id.trim();
+    if let Ok(id) = id.parse::<usize>() {
+        return Ok(id);
+    }
+    if let Some(number) = id.strip_prefix('@') {
+        let uuids = read_display_ids()?;
+        let uuid = number
+            .parse::<usize>()
+            .ok()
+            .and_then(|number| uuids.get(number.checked_sub(1)?))
+            .ok_or_else(|| format!("Display id {} is not in the last listing", id))?;
+        // display ids point at UUIDs, so they survive renumbering
+        return tasks
+            .values()
+            .find(|task| task.uuid == *uuid)
+            .map(|task| task.id)
+            .ok_or_else(|| format!("Task {} of the last listing no longer exists", id).into());
+    }
+    let prefix = id.to_lowercase();
+    let matches = match prefix.is_empty() {
+        true => vec![],
+        false => tasks
+            .values()
+            .filter(|task| task.uuid.to_lowercase().starts_with(&prefix))
+            .map(|task| task.id)
+            .collect::<Vec<_>>(),
+    };
+    match matches[..] {
+        [] => Err(format!("task with id {} not found", id).into()),
+        [id] => Ok(id),
+        _ => {
+            let ids = matches.iter().map(|id| id.to_string()).collect::<Vec<_>>();
+            Err(format!("UUID prefix {} is ambiguous, it matches tasks {}", id, ids.join(", ")).into())
+        }
+    }
+}
+
+/// Parse a list of ids, UUID prefixes and inclusive id ranges, e.g. `3,5,8-12`.
+/// A range stands for the existing tasks with ids within it.
+pub fn parse_ids(spec: &str, tasks: &BTreeMap<usize, Task>) -> TodoResult<Vec<usize>> {
+    let mut ids = vec![];
+    for part in spec.split(',').filter(|part| !part.trim().is_empty()) {
+        // UUIDs contain dashes too, only ids on both sides make a range
+        let range = part.split_once('-').and_then(|(start, end)| {
+            Some((start.trim().parse::<usize>().ok()?, end.trim().parse::<usize>().ok()?))
+        });
+        match range {
+            Some((start, end)) => {
+                if start > end {
+                    return Err(format!("Invalid id range {}", part).into());
+                }
+                // ranges skip ids no task has, so they work across gaps
+                let len = ids.len();
+                ids.extend(tasks.range(start..=end).map(|(id, _)| *id));
+                if ids.len() == len {
+                    return Err(format!("No tasks with ids in range {}", part).into());
+                }
+            }
+            None => ids.push(parse_id(part, tasks)?),
+        }
+    }
+    if ids.is_empty() {
+        return Err("No task id given".into());
+    }
+    ids.sort();
+    ids.dedup();
+    Ok(ids)
+}
+
+/// Conditions parsed from a `--where` expression, all of them must hold for a
+/// task to be selected
+#[derive(Debug, Default)]
+pub struct TaskQuery {
+    tags: Vec<String>,
+    priority: Option<PriorityFilter>,
+    done: Option<bool>,
+    state: Option<String>,
+    due_before: Option<NaiveDate>,
+    text: Vec<String>,
+}
+
+impl TaskQuery {
+    /// Parse whitespace separated `key:value` terms. Supported keys are `tag`
+    /// (matching nested tags too), `priority` (e.g. `priority:>=high`), `done`,
+    /// `state` and `due-before`, bare words are matched against task names,
+    /// tags, notes and annotations.
+    pub fn parse(expression: &str, date_format: &str) -> TodoResult<Self> {
+        let mut query = TaskQuery::default();
+        for term in expression.split_whitespace() {
+            match term.split_once(':') {
+                Some(("tag", tag)) => query.tags.push(tag.to_lowercase()),
+                Some(("priority", value)) => query.priority = Some(value.parse()?),
+                Some(("done", value)) => query.done = Some(parse_bool(term, value)?),
+                Some(("state", state)) => query.state = Some(state.to_string()),
+                Some(("due-before", date)) => {
+                    query.due_before = Some(
+                        NaiveDate::parse_from_str(date, date_format)
+                            .map_err(|_| format!("Invalid date in {}, expected format: {}", term, date_format))?,
+                    )
+                }
+                Some((key, _)) => return Err(format!("Unknown filter key {}", key).into()),
+                None => query.text.push(term.to_lowercase()),
+            }
+        }
+        Ok(query)
+    }
+
+    pub fn matches(&self, task: &Task) -> bool {
+        self.tags.iter().all(|tag| task.has_tag(tag))
+            && self.priority.is_none_or(|priority| priority.matches(task.priority))
+            && self.done.is_none_or(|done| task.done == done)
+            && self.state.as_ref().is_none_or(|state| task.state == *state)
+            && self
+                .due_before
+                .is_none_or(|date| task.is_due_before_given_date(&date))
+            && self.text.iter().all(|text| task.matches(text))
+    }
+}
+
+fn parse_bool(term: &str, value: &str) -> TodoResult<bool> {
+    value
+        .parse::<bool>()
+        .map_err(|_| format!("Invalid value in {}, expected true or false", term).into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Priority, Workflow, DEFAULT_DATE_FORMAT};
+
+    fn tasks(ids: &[usize]) -> BTreeMap<usize, Task> {
+        ids.iter()
+            .map(|&id| {
+                let task = Task::new(
+                    id,
+                    format!("task {}", id),
+                    Priority::None,
+                    None,
+                    None,
+                    DEFAULT_DATE_FORMAT,
+                    &Workflow::default(),
+                )
+                .unwrap();
+                (id, task)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn parses_ids_and_ranges() {
+        let tasks = tasks(&[1, 2, 3, 5, 8, 9, 10, 12]);
+        assert_eq!(parse_ids("3,5,8-10", &tasks).unwrap(), vec![3, 5, 8, 9, 10]);
+        assert_eq!(parse_ids(" 2 , 1-2 ,", &tasks).unwrap(), vec![1, 2]);
+    }
+
+    #[test]
+    fn ranges_skip_missing_ids() {
+        let tasks = tasks(&[1, 4, 7]);
+        assert_eq!(parse_ids("1-7", &tasks).unwrap(), vec![1, 4, 7]);
+        assert_eq!(parse_ids("2-5", &tasks).unwrap(), vec![4]);
+    }
+
+    #[test]
+    fn huge_ranges_only_visit_existing_tasks() {
+        let tasks = tasks(&[3, 1000]);
+        assert_eq!(parse_ids("1-999999999999", &tasks).unwrap(), vec![3, 1000]);
+        assert_eq!(parse_ids(&format!("0-{}", usize::MAX), &tasks).unwrap(), vec![3, 1000]);
+    }
+
+    #[test]
+    fn rejects_empty_and_reversed_ranges() {
+        let tasks = tasks(&[1, 2, 10]);
+        assert!(parse_ids("3-9", &tasks).is_err());
+        assert!(parse_ids("10-2", &tasks).is_err());
+        assert!(parse_ids(",", &tasks).is_err());
+    }
+
+    #[test]
+    fn resolves_uuid_prefixes() {
+        let mut tasks = tasks(&[1, 2, 3]);
+        tasks.get_mut(&1).unwrap().uuid = "ab12cd34-0000-4000-8000-000000000001".to_string();
+        tasks.get_mut(&2).unwrap().uuid = "ab12ef56-0000-4000-8000-000000000002".to_string();
+        tasks.get_mut(&3).unwrap().uuid = "12345678-0000-4000-8000-000000000003".to_string();
+        assert_eq!(parse_ids("3,ab12e", &tasks).unwrap(), vec![2, 3]);
+        assert_eq!(parse_ids("ab12cd34-0000-4000-8000-000000000001", &tasks).unwrap(), vec![1]);
+        assert_eq!(parse_id("AB12C", &tasks).unwrap(), 1);
+        assert!(parse_id("ab12", &tasks).is_err());
+        // digits only are read as an id, not as a prefix
+        assert_eq!(parse_id("12345678", &tasks).unwrap(), 12345678);
+    }
+}
@@ -1,28 +1,100 @@
-use crate::TodoResult;
-use crate::app::DEFAULT_DATE_FORMAT;
+use crate::{Priority, TodoResult};
+use crate::app::{Workflow, DEFAULT_DATE_FORMAT, DEFAULT_TIMESTAMP_FORMAT};
 use chrono::NaiveDate;
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
+use uuid::Uuid;
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Task {
     pub id: usize,
+    /// Globally unique identity, kept when ids change and when tasks are
+    /// imported, exported, merged or restored
+    #[serde(default, deserialize_with = "deserialize_uuid")]
+    pub uuid: String,
     pub name: String,
-    pub priority: bool,
+    pub priority: Priority,
     pub due_date: Option<String>,
     pub tags: Option<Vec<String>>,
     pub done: bool,
     pub created_at: String,
     pub updated_at: Option<String>,
+    #[serde(default)]
+    pub notes: Option<String>,
+    /// Workflow state, `done` is kept in sync with it
+    #[serde(default)]
+    pub state: String,
+    #[serde(default)]
+    pub history: Vec<StateChange>,
+    /// When the task was last moved to the done state, cleared when it leaves it
+    #[serde(default)]
+    pub completed_at: Option<String>,
+    #[serde(default)]
+    pub annotations: Vec<Annotation>,
+}
+
+/// A timestamped note appended to a task
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Annotation {
+    pub at: String,
+    pub text: String,
+}
+
+/// A move of a task from one workflow state to another
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct StateChange {
+    pub from: String,
+    pub to: String,
+    pub at: String,
+}
+
+/// Changes to apply to a task, `None` keeps the current value and an empty
+/// value clears optional fields
+#[derive(Debug, Default, Clone)]
+pub struct TaskUpdate {
+    pub name: Option<String>,
+    pub priority: Option<Priority>,
+    pub due_date: Option<String>,
+    pub tags: Option<Vec<String>>,
+    pub add_tags: Vec<String>,
+    pub remove_tags: Vec<String>,
+    pub clear_tags: bool,
+    pub done: Option<bool>,
+    pub state: Option<String>,
+    pub notes: Option<String>,
+}
+
+/// Current local time in the format timestamps are stored with
+pub fn timestamp() -> String {
+    chrono::Local::now()
+        .format(DEFAULT_TIMESTAMP_FORMAT)
+        .to_string()
+}
+
+/// Read a UUID that may be missing or `null`, as tasks imported before every
+/// task had one stored it, leaving it empty for `AppData::migrate` to fill in
+fn deserialize_uuid<'de, D: Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+    Ok(Option::<String>::deserialize(deserializer)?.unwrap_or_default())
+}
+
+/// Check whether `tag` is `parent` itself or nested below it, e.g. `work/api`
+/// is within `work`. Tags are compared case-insensitively.
+pub fn is_tag_within(tag: &str, parent: &str) -> bool {
+    let (tag, parent) = (tag.to_lowercase(), parent.to_lowercase());
+    tag == parent
+        || tag
+            .strip_prefix(&parent)
+            .is_some_and(|rest| rest.starts_with('/'))
 }
 
 impl Task {
     pub fn new(
         id: usize,
         name: String,
-        priority: bool,
+        priority: Priority,
         due_date: Option<String>,
         tags: Option<Vec<String>>,
         date_format: &str,
+        workflow: &Workflow,
     ) -> TodoResult<Self> {
         let due_date = match due_date {
             Some(due_date) => Some(
@@ -32,64 +104,152 @@ impl Task {
         };
         let task = Task {
             id,
+            uuid: Uuid::new_v4().to_string(),
             name,
             priority,
             due_date,
-            tags,
+            tags: Task::normalize_tags(tags.unwrap_or_default()),
             done: false,
-            created_at: chrono::Local::now()
-                .format(&format!("{} %H:%M:%S", DEFAULT_DATE_FORMAT))
-                .to_string(),
+            created_at: timestamp(),
             updated_at: None,
+            notes: None,
+            state: workflow.initial.clone(),
+            history: vec![],
+            completed_at: None,
+            annotations: vec![],
         };
         Ok(task)
     }
 
+    /// UUID for a task stored before tasks had one, derived from its id and
+    /// creation time so it matches the one earlier exports gave it
+    pub fn legacy_uuid(&self) -> String {
+        let name = format!("{}:{}", self.id, self.created_at);
+        Uuid::new_v5(&Uuid::NAMESPACE_OID, name.as_bytes()).to_string()
+    }
+
     pub fn edit(
         &mut self,
-        name: Option<String>,
-        priority: Option<bool>,
-        due_date: Option<String>,
-        tags: Option<Vec<String>>,
-        done: Option<bool>,
+        update: TaskUpdate,
         date_format: &str,
+        workflow: &Workflow,
     ) -> TodoResult<()> {
-        if let Some(name) = name {
+        // validate before touching the task so a bad date leaves it unchanged
+        let due_date = match update.due_date {
+            Some(due_date) if due_date.is_empty() => Some(None),
+            Some(due_date) => Some(Some(
+                Task::parse_due_date(&due_date, date_format)?.format(DEFAULT_DATE_FORMAT).to_string(),
+            )),
+            None => None,
+        };
+        // an explicit state wins, completing or reopening only moves tasks that need it
+        let state = match (update.state, update.done) {
+            (Some(state), _) if state != self.state => Some(state),
+            (_, Some(true)) if !self.done => Some(workflow.done.clone()),
+            (_, Some(false)) if self.done => Some(workflow.initial.clone()),
+            _ => None,
+        };
+        if let Some(state) = &state {
+            workflow.check_transition(&self.state, state)?;
+        }
+        if let Some(name) = update.name {
             self.name = name;
         }
-        if let Some(priority) = priority {
+        if let Some(priority) = update.priority {
             self.priority = priority;
         }
         if let Some(due_date) = due_date {
-            self.due_date = Some(
-                Task::parse_due_date(&due_date, date_format)?.format(DEFAULT_DATE_FORMAT).to_string(),
-            );
+            self.due_date = due_date;
         }
-        if let Some(tags) = tags {
-            self.tags = Some(tags);
+        // tags are replaced or cleared first, then the incremental changes apply
+        let mut tags = match (update.clear_tags, update.tags) {
+            (true, _) => vec![],
+            (false, Some(tags)) => tags,
+            (false, None) => self.tags.take().unwrap_or_default(),
+        };
+        tags.retain(|tag| {
+            !update
+                .remove_tags
+                .iter()
+                .any(|removed| removed.to_lowercase() == tag.to_lowercase())
+        });
+        tags.extend(update.add_tags);
+        self.tags = Task::normalize_tags(tags);
+        if let Some(state) = state {
+            self.set_state(state, workflow);
+        }
+        if let Some(notes) = update.notes {
+            self.notes = Some(notes).filter(|notes| !notes.is_empty());
         }
-        if let Some(done) = done {
-            self.done = done;
+        self.updated_at = Some(timestamp());
+        Ok(())
+    }
+
+    /// Move the task to given state if the workflow allows it
+    pub fn move_to(&mut self, state: &str, workflow: &Workflow) -> TodoResult<()> {
+        if self.state != state {
+            workflow.check_transition(&self.state, state)?;
+            self.set_state(state.to_string(), workflow);
+            self.updated_at = Some(timestamp());
         }
-        self.updated_at = Some(
-            chrono::Local::now()
-                .format(&format!("{} %H:%M:%S", DEFAULT_DATE_FORMAT))
-                .to_string(),
-        );
         Ok(())
     }
 
+    fn set_state(&mut self, state: String, workflow: &Workflow) {
+        self.history.push(StateChange {
+            from: std::mem::take(&mut self.state),
+            to: state.clone(),
+            at: timestamp(),
+        });
+        self.done = state == workflow.done;
+        self.completed_at = match self.done {
+            true => Some(timestamp()),
+            false => None,
+        };
+        self.state = state;
+    }
+
+    /// Append a timestamped note
+    pub fn annotate(&mut self, text: String) {
+        self.annotations.push(Annotation {
+            at: timestamp(),
+            text,
+        });
+        self.updated_at = Some(timestamp());
+    }
+
+    /// Check whether name, any tag, the notes or any annotation of the task
+    /// contains given lowercase query
+    pub fn matches(&self, query: &str) -> bool {
+        self.name.to_lowercase().contains(query)
+            || self
+                .tags
+                .iter()
+                .flatten()
+                .any(|tag| tag.to_lowercase().contains(query))
+            || self
+                .notes
+                .as_ref()
+                .is_some_and(|notes| notes.to_lowercase().contains(query))
+            || self
+                .annotations
+                .iter()
+                .any(|annotation| annotation.text.to_lowercase().contains(query))
+    }
+
+    /// Check whether the task carries given tag or one nested below it
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.iter().flatten().any(|t| is_tag_within(t, tag))
+    }
+
     pub fn is_due_today(&self) -> bool {
         self.is_due_before_given_date(&chrono::Local::now().date_naive())
     }
 
     pub fn get_parsed_due_date(&self) -> Option<NaiveDate> {
-        match &self.due_date {
-            Some(due_date) => Some(
-                NaiveDate::parse_from_str(due_date, DEFAULT_DATE_FORMAT).unwrap(),
-            ),
-            None => None,
-        }
+        self.due_date
+            .as_ref()
+            .map(|due_date| NaiveDate::parse_from_str(due_date, DEFAULT_DATE_FORMAT).unwrap())
     }
 
     pub fn is_due_before_given_date(&self, given_due_date: &NaiveDate) -> bool {
@@ -102,6 +262,18 @@ impl Task {
         }
     }
 
+    /// Drop blank and case-insensitively repeated tags, `None` if nothing is left
+    fn normalize_tags(tags: Vec<String>) -> Option<Vec<String>> {
+        let mut normalized: Vec<String> = vec![];
+        for tag in tags {
+            let tag = tag.trim();
+            if !tag.is_empty() && !normalized.iter().any(|t| t.to_lowercase() == tag.to_lowercase()) {
+                normalized.push(tag.to_string());
+            }
+        }
+        Some(normalized).filter(|tags| !tags.is_empty())
+    }
+
     fn parse_due_date(due_date: &str, date_format: &str) -> TodoResult<NaiveDate> {
         match NaiveDate::parse_from_str(due_date, date_format) {
             Ok(date) => Ok(date),
@@ -1,10 +1,72 @@
-use crate::{tasks::Task, AppData, TodoResult};
-use chrono::NaiveDate;
+use crate::{formats::ImportedTask, tasks::is_tag_within, tasks::parse_duration, tasks::parse_since, tasks::parse_timestamp, tasks::Priority, tasks::PriorityFilter, tasks::Task, tasks::TaskQuery, tasks::TaskUpdate, AppData, TagInfo, TodoResult};
+use colored::Color;
+use std::collections::{BTreeSet, HashSet};
+use crate::app::{append_to_archive, DEFAULT_DATE_FORMAT, DEFAULT_TIMESTAMP_FORMAT};
+use chrono::{Duration, NaiveDate, NaiveDateTime};
+use uuid::Uuid;
 
 pub struct TaskManager {
     pub app_data: AppData,
 }
 
+/// Outcome of an import, `tasks` are the ones added or, in a dry run, the
+/// ones that would be
+#[derive(Debug, Default)]
+pub struct ImportResult {
+    pub tasks: Vec<Task>,
+    pub duplicates: Vec<ImportedTask>,
+    /// Tasks that couldn't be created
+    pub errors: Vec<String>,
+}
+
+/// Which tasks a reset removes, every task when nothing is set
+#[derive(Debug, Default)]
+pub struct ResetScope {
+    /// Only tasks that are done
+    pub done_only: bool,
+    /// Only tasks carrying this tag or one nested below it
+    pub tag: Option<String>,
+    /// Only tasks created before this date
+    pub before: Option<String>,
+}
+
+impl ResetScope {
+    pub fn is_empty(&self) -> bool {
+        !self.done_only && self.tag.is_none() && self.before.is_none()
+    }
+}
+
+/// Tag conditions of a listing, tags are matched case-insensitively along
+/// with the tags nested below them
+#[derive(Debug, Default)]
+pub struct TagFilter {
+    /// Tasks have to carry every one of these
+    pub all: Vec<String>,
+    /// Tasks have to carry at least one of these
+    pub any: Vec<String>,
+    /// Tasks must not carry any of these
+    pub exclude: Vec<String>,
+}
+
+/// A tag with the number of open and done tasks carrying it or a tag below it,
+/// cancelled tasks count as neither
+#[derive(Debug)]
+pub struct TagSummary {
+    pub name: String,
+    pub open: usize,
+    pub done: usize,
+    /// Nesting level, 0 for top level tags
+    pub depth: usize,
+    pub info: TagInfo,
+}
+
+fn validate_tag_name(name: &str) -> TodoResult<&str> {
+    match name.trim() {
+        "" => Err("Tag name can't be empty".into()),
+        name => Ok(name),
+    }
+}
+
 impl TaskManager {
     pub fn new(app_data: AppData) -> Self {
         Self { app_data }
@@ -13,10 +75,10 @@ impl TaskManager {
     pub fn add_task(
         &mut self,
         name: String,
-        priority: bool,
+        priority: Priority,
         due_date: Option<String>,
         tags: Option<Vec<String>>,
-    ) -> TodoResult<()> {
+    ) -> TodoResult<usize> {
         let task = Task::new(
             self.app_data.next_id,
             name,
@@ -24,123 +86,648 @@ impl TaskManager {
             due_date,
             tags,
             &self.app_data.config.date_format,
+            &self.app_data.config.workflow,
         )?;
+        let id = self.insert_task(task);
+        self.app_data.save()?;
+        Ok(id)
+    }
+
+    /// Add a task built with the next id and index it, without saving
+    fn insert_task(&mut self, task: Task) -> usize {
         self.app_data.next_id += 1;
-        if let Some(tags) = &task.tags {
-            for tag in tags {
-                let tag_tasks = self
-                    .app_data
-                    .tags
-                    .entry(tag.to_lowercase())
-                    .or_insert(vec![]);
-                tag_tasks.push(task.id);
+        let id = task.id;
+        self.app_data.tasks.insert(id, task);
+        self.index_task(id);
+        id
+    }
+
+    /// Replace all data with restored data, repairing whatever is
+    /// inconsistent in it
+    pub fn replace_data(&mut self, app_data: AppData) -> TodoResult<()> {
+        self.app_data = app_data;
+        self.repair()
+    }
+
+    /// Add the tasks of restored data that aren't here yet under new ids, and
+    /// the tag details missing here, keeping everything else. Returns the ids
+    /// of the added tasks.
+    pub fn merge_data(&mut self, app_data: AppData) -> TodoResult<Vec<usize>> {
+        let known = self
+            .app_data
+            .tasks
+            .values()
+            .map(|task| task.uuid.clone())
+            .collect::<BTreeSet<_>>();
+        let mut ids = vec![];
+        for task in app_data.tasks.into_values() {
+            if known.contains(&task.uuid) {
+                continue;
             }
+            // the UUID is kept so merging the same backup again adds nothing
+            let task = Task {
+                id: self.app_data.next_id,
+                ..task
+            };
+            ids.push(self.insert_task(task));
+        }
+        for (tag, info) in app_data.tag_info {
+            self.app_data.tag_info.entry(tag).or_insert(info);
         }
-        self.app_data.tasks.insert(task.id, task);
+        self.repair()?;
+        Ok(ids)
+    }
+
+    /// Add imported tasks and save once, skipping the ones whose UUID or, if
+    /// they have none, name and due date match an existing task or one imported
+    /// before them
+    pub fn import_tasks(&mut self, imported: Vec<ImportedTask>, dry_run: bool) -> TodoResult<ImportResult> {
+        let config = &self.app_data.config;
+        let mut seen = self
+            .app_data
+            .tasks
+            .values()
+            .map(|task| (task.name.trim().to_lowercase(), task.get_parsed_due_date()))
+            .collect::<BTreeSet<_>>();
+        let mut seen_uuids = self
+            .app_data
+            .tasks
+            .values()
+            .map(|task| task.uuid.clone())
+            .collect::<BTreeSet<_>>();
+        let mut result = ImportResult::default();
+        for imported in imported {
+            let duplicate = match &imported.uuid {
+                Some(uuid) => !seen_uuids.insert(uuid.clone()),
+                None => !seen.insert((imported.name.trim().to_lowercase(), imported.due_date)),
+            };
+            if duplicate {
+                result.duplicates.push(imported);
+                continue;
+            }
+            let location = match imported.line {
+                Some(line) => format!("line {}", line),
+                None => format!("task {:?}", imported.name),
+            };
+            let task = Task::new(
+                self.app_data.next_id + result.tasks.len(),
+                imported.name,
+                imported.priority,
+                imported.due_date.map(|date| date.format(&config.date_format).to_string()),
+                Some(imported.tags),
+                &config.date_format,
+                &config.workflow,
+            );
+            let mut task = match task {
+                Ok(task) => task,
+                Err(e) => {
+                    result.errors.push(format!("{}: {}", location, e));
+                    continue;
+                }
+            };
+            let timestamp = |at: NaiveDateTime| at.format(DEFAULT_TIMESTAMP_FORMAT).to_string();
+            if let Some(uuid) = imported.uuid {
+                task.uuid = uuid;
+            }
+            task.annotations = imported.annotations;
+            task.notes = imported.notes;
+            if let Some(created_at) = imported.created_at {
+                task.created_at = timestamp(created_at);
+            }
+            let state = match (&imported.state, imported.done, imported.cancelled, imported.started) {
+                (Some(state), _, _, _) => Some(state),
+                (None, true, _, _) => Some(&config.workflow.done),
+                (None, false, true, _) => Some(&config.workflow.cancelled),
+                (None, false, false, true) => Some(&config.workflow.started),
+                (None, false, false, false) => None,
+            };
+            let moved = state.map_or(Ok(()), |state| task.move_to(state, &config.workflow));
+            if let Err(e) = moved {
+                result.errors.push(format!("{}: {}", location, e));
+                continue;
+            }
+            if let Some(completed_at) = imported.completed_at.filter(|_| task.done) {
+                task.completed_at = Some(timestamp(completed_at));
+            }
+            if let Some(updated_at) = imported.updated_at {
+                task.updated_at = Some(timestamp(updated_at));
+            }
+            result.tasks.push(task);
+        }
+        if !dry_run && !result.tasks.is_empty() {
+            for task in &result.tasks {
+                self.insert_task(task.clone());
+            }
+            self.app_data.save()?;
+        }
+        Ok(result)
+    }
+
+    pub fn edit_task(&mut self, id: usize, update: TaskUpdate) -> TodoResult<()> {
+        self.edit_tasks(&[id], update)
+    }
+
+    /// Apply the same update to every given task and save once
+    pub fn edit_tasks(&mut self, ids: &[usize], update: TaskUpdate) -> TodoResult<()> {
+        self.check_ids(ids)?;
+        self.update_tasks(ids, &update)?;
         self.app_data.save()?;
         Ok(())
     }
 
-    pub fn edit_task(
-        &mut self,
-        id: usize,
-        name: Option<String>,
-        priority: Option<bool>,
-        due_date: Option<String>,
-        tags: Option<Vec<String>>,
-        done: Option<bool>,
-    ) -> TodoResult<()> {
-        match self.app_data.tasks.get_mut(&id) {
-            Some(task) => {
-                task.edit(
-                    name,
-                    priority,
-                    due_date,
-                    tags,
-                    done,
-                    &self.app_data.config.date_format,
-                )?;
-                if let Some(tags) = &task.tags {
-                    for tag in tags {
-                        let tag_tasks = self
-                            .app_data
-                            .tags
-                            .entry(tag.to_lowercase())
-                            .or_insert(vec![]);
-                        tag_tasks.push(task.id);
-                    }
-                }
-                self.app_data.save()?;
-                Ok(())
-            },
-            None => return Err(format!("task with id {} not found", id).into()),
+    /// Apply given update to existing tasks and reindex them, without saving
+    fn update_tasks(&mut self, ids: &[usize], update: &TaskUpdate) -> TodoResult<()> {
+        for id in ids {
+            let task = self.app_data.tasks.get_mut(id).unwrap();
+            let config = &self.app_data.config;
+            task.edit(update.clone(), &config.date_format, &config.workflow)?;
+            self.unindex_task(*id);
+            self.index_task(*id);
         }
+        Ok(())
     }
 
     pub fn remove_task(&mut self, id: usize) -> TodoResult<()> {
-        match self.app_data.tasks.remove(&id) {
-            Some(task) => {
-                if let Some(tags) = &task.tags {
-                    for tag in tags {
-                        if let Some(tag_tasks) = self.app_data.tags.get_mut(&tag.to_lowercase()) {
-                            tag_tasks.retain(|&x| x != id);
-                        }
-                    }
-                }
-                // if any of the tag has no entry, remove it
-                self.app_data.tags.retain(|_, v| !v.is_empty());
-                self.app_data.save()?;
-                Ok(())
-            }
-            None => Err(format!("task with id {} not found", id).into()),
+        self.remove_tasks(&[id])
+    }
+
+    /// Remove every given task and save once
+    pub fn remove_tasks(&mut self, ids: &[usize]) -> TodoResult<()> {
+        self.check_ids(ids)?;
+        for id in ids {
+            self.app_data.tasks.remove(id);
+            self.unindex_task(*id);
         }
+        self.app_data.save()?;
+        Ok(())
+    }
+
+    /// Ids of the tasks a reset with given scope removes
+    pub fn tasks_to_reset(&self, scope: &ResetScope) -> TodoResult<Vec<usize>> {
+        let before = match &scope.before {
+            Some(before) => Some(
+                NaiveDate::parse_from_str(before, &self.app_data.config.date_format)
+                    .map_err(|_| format!("Invalid date. Date should be in this format: {}", self.app_data.config.date_format))?,
+            ),
+            None => None,
+        };
+        let ids = self
+            .app_data
+            .tasks
+            .values()
+            .filter(|task| !scope.done_only || task.done)
+            .filter(|task| scope.tag.as_ref().is_none_or(|tag| task.has_tag(tag)))
+            .filter(|task| {
+                before.is_none_or(|before| {
+                    parse_timestamp(&task.created_at).is_some_and(|created_at| created_at.date() < before)
+                })
+            })
+            .map(|task| task.id)
+            .collect();
+        Ok(ids)
     }
 
     pub fn reset_tasks(&mut self) -> TodoResult<()> {
         self.app_data.tasks.clear();
         self.app_data.tags.clear();
+        // details of tags would otherwise come back when a tag name is reused
+        self.app_data.tag_info.clear();
         self.app_data.next_id = 1;
         self.app_data.save()?;
         Ok(())
     }
 
-    pub fn mark_done(&mut self, id: usize) -> TodoResult<()> {
-        match self.app_data.tasks.get_mut(&id) {
-            Some(task) => {
-                task.done = true;
-                self.app_data.save()?;
-                Ok(())
+    /// Give open tasks, or all tasks, the lowest ids not taken by the others,
+    /// keeping their order, and return the old and new id of each task moved
+    pub fn renumber_tasks(&mut self, all: bool) -> TodoResult<Vec<(usize, usize)>> {
+        let workflow = &self.app_data.config.workflow;
+        let (moving, kept): (Vec<Task>, Vec<Task>) = std::mem::take(&mut self.app_data.tasks)
+            .into_values()
+            .partition(|task| all || workflow.is_open(&task.state));
+        let taken = kept.iter().map(|task| task.id).collect::<BTreeSet<_>>();
+        self.app_data.tasks = kept.into_iter().map(|task| (task.id, task)).collect();
+        let mut free_ids = (1..).filter(|id| !taken.contains(id));
+        let mut moves = vec![];
+        for mut task in moving {
+            let id = free_ids.next().unwrap();
+            if task.id != id {
+                moves.push((task.id, id));
+                task.id = id;
             }
-            None => Err(format!("task with id {} not found", id).into()),
+            self.app_data.tasks.insert(id, task);
         }
+        self.app_data.next_id = self.app_data.tasks.keys().max().map_or(1, |max_id| max_id + 1);
+        self.rebuild_tag_index();
+        self.app_data.save()?;
+        Ok(moves)
     }
 
-    pub fn list_tasks_by_tags(&self, tags: &[String]) -> Vec<&Task> {
-        let mut tasks = vec![];
-        for tag in tags {
-            if let Some(tag_tasks) = self.app_data.tags.get(tag) {
-                for task_id in tag_tasks {
-                    if let Some(task) = self.app_data.tasks.get(task_id) {
-                        tasks.push(task);
-                    }
-                }
+    /// Move done tasks completed longer ago than given duration, or all of
+    /// them, to the archive and return their ids
+    pub fn archive_tasks(&mut self, older_than: Option<Duration>) -> TodoResult<Vec<usize>> {
+        let now = chrono::Local::now().naive_local();
+        let tasks = self
+            .app_data
+            .tasks
+            .values()
+            .filter(|task| task.done)
+            .filter(|task| {
+                // tasks completed before completion was recorded count from their last update
+                let completed_at = task
+                    .completed_at
+                    .as_ref()
+                    .or(task.updated_at.as_ref())
+                    .unwrap_or(&task.created_at);
+                older_than.is_none_or(|older_than| {
+                    parse_timestamp(completed_at).is_some_and(|completed_at| now - completed_at > older_than)
+                })
+            })
+            .collect::<Vec<_>>();
+        if tasks.is_empty() {
+            return Ok(vec![]);
+        }
+        // the archive is written first so a failure can't lose tasks
+        append_to_archive(&tasks)?;
+        let ids = tasks.iter().map(|task| task.id).collect::<Vec<_>>();
+        self.remove_tasks(&ids)?;
+        Ok(ids)
+    }
+
+    /// Archive done tasks as configured by `archive_after`, if at all
+    pub fn auto_archive(&mut self) -> TodoResult<()> {
+        if let Some(archive_after) = self.app_data.config.archive_after.clone() {
+            let older_than = parse_duration(&archive_after)
+                .map_err(|e| format!("Invalid archive_after in config: {}, run `todo doctor` for details", e))?;
+            self.archive_tasks(Some(older_than))?;
+        }
+        Ok(())
+    }
+
+    /// Mark every given task as done and save once
+    pub fn mark_done(&mut self, ids: &[usize]) -> TodoResult<()> {
+        let done = self.app_data.config.workflow.done.clone();
+        self.move_tasks(ids, &done)
+    }
+
+    /// Append a timestamped note to a task
+    pub fn annotate_task(&mut self, id: usize, text: String) -> TodoResult<()> {
+        if text.trim().is_empty() {
+            return Err("Note can't be empty".into());
+        }
+        self.check_ids(&[id])?;
+        self.app_data.tasks.get_mut(&id).unwrap().annotate(text);
+        self.app_data.save()?;
+        Ok(())
+    }
+
+    /// Other tasks carrying any of the tags of given task, ordered by id
+    pub fn related_tasks(&self, id: usize) -> Vec<&Task> {
+        let tags = self.app_data.tasks[&id].tags.clone().unwrap_or_default();
+        let mut ids = tags
+            .iter()
+            .filter_map(|tag| self.app_data.tags.get(&tag.to_lowercase()))
+            .flatten()
+            .filter(|other| **other != id)
+            .copied()
+            .collect::<Vec<_>>();
+        ids.sort();
+        ids.dedup();
+        ids.iter().filter_map(|id| self.app_data.tasks.get(id)).collect()
+    }
+
+    /// Move done and cancelled tasks back to the initial state
+    pub fn reopen_tasks(&mut self, ids: &[usize]) -> TodoResult<()> {
+        let initial = self.app_data.config.workflow.initial.clone();
+        self.move_tasks(ids, &initial)
+    }
+
+    /// Move every given task to a workflow state and save once, nothing
+    /// changes unless the workflow allows the move for all of them
+    pub fn move_tasks(&mut self, ids: &[usize], state: &str) -> TodoResult<()> {
+        self.check_ids(ids)?;
+        let workflow = &self.app_data.config.workflow;
+        workflow.check_state(state)?;
+        for id in ids {
+            let task = &self.app_data.tasks[id];
+            if task.state != state {
+                workflow
+                    .check_transition(&task.state, state)
+                    .map_err(|e| format!("task {}: {}", id, e))?;
             }
         }
-        tasks
+        for id in ids {
+            let task = self.app_data.tasks.get_mut(id).unwrap();
+            task.move_to(state, &self.app_data.config.workflow)?;
+        }
+        self.app_data.save()?;
+        Ok(())
+    }
+
+    /// Ids of all tasks matching given query
+    pub fn find_tasks(&self, query: &TaskQuery) -> Vec<usize> {
+        self.app_data
+            .tasks
+            .values()
+            .filter(|task| query.matches(task))
+            .map(|task| task.id)
+            .collect()
+    }
+
+    /// Rebuild derived state from the tasks themselves: ids from their keys,
+    /// `next_id`, the tag index, `done` from the state, and drop due dates that
+    /// can't be read. Tasks in unknown states are put back to the initial or
+    /// done state.
+    pub fn repair(&mut self) -> TodoResult<()> {
+        // moving tasks to states of a broken workflow would lose their states
+        self.app_data.check_config()?;
+        let workflow = &self.app_data.config.workflow;
+        let mut uuids = HashSet::new();
+        for (key, task) in self.app_data.tasks.iter_mut() {
+            task.id = *key;
+            // the first task keeps a UUID given to several, the others get a new one
+            if !uuids.insert(task.uuid.clone()) {
+                task.uuid = Uuid::new_v4().to_string();
+                uuids.insert(task.uuid.clone());
+            }
+            if workflow.check_state(&task.state).is_err() {
+                task.state = match task.done {
+                    true => workflow.done.clone(),
+                    false => workflow.initial.clone(),
+                };
+            }
+            task.done = task.state == workflow.done;
+            if !task.done {
+                task.completed_at = None;
+            }
+            let invalid_due_date = task
+                .due_date
+                .as_ref()
+                .is_some_and(|due_date| NaiveDate::parse_from_str(due_date, DEFAULT_DATE_FORMAT).is_err());
+            if invalid_due_date {
+                task.due_date = None;
+            }
+        }
+        if let Some(max_id) = self.app_data.tasks.keys().max() {
+            self.app_data.next_id = self.app_data.next_id.max(max_id + 1);
+        }
+        let config = &mut self.app_data.config;
+        if config.archive_after.as_deref().is_some_and(|after| parse_duration(after).is_err()) {
+            config.archive_after = None;
+        }
+        self.rebuild_tag_index();
+        self.app_data.save()?;
+        Ok(())
+    }
+
+    fn rebuild_tag_index(&mut self) {
+        self.app_data.tags.clear();
+        let ids = self.app_data.tasks.keys().copied().collect::<Vec<_>>();
+        for id in ids {
+            self.index_task(id);
+        }
+    }
+
+    /// Add given task to the index entry of each of its tags
+    fn index_task(&mut self, id: usize) {
+        let task = &self.app_data.tasks[&id];
+        for tag in task.tags.iter().flatten() {
+            let tag_tasks = self.app_data.tags.entry(tag.to_lowercase()).or_default();
+            if !tag_tasks.contains(&id) {
+                tag_tasks.push(id);
+            }
+        }
+    }
+
+    /// Drop given task from the tag index, removing tags left without tasks
+    fn unindex_task(&mut self, id: usize) {
+        for tag_tasks in self.app_data.tags.values_mut() {
+            tag_tasks.retain(|x| *x != id);
+        }
+        self.app_data.tags.retain(|_, v| !v.is_empty());
+    }
+
+    /// Ensure every given id belongs to an existing task
+    pub fn check_ids(&self, ids: &[usize]) -> TodoResult<()> {
+        match ids.iter().find(|id| !self.app_data.tasks.contains_key(id)) {
+            Some(id) => Err(format!("task with id {} not found", id).into()),
+            None => Ok(()),
+        }
+    }
+
+    /// Tasks carrying any of given tags, each task listed once
+    pub fn list_tasks_by_tags(&self, tags: &[String]) -> Vec<&Task> {
+        self.task_ids_within_tags(tags)
+            .iter()
+            .filter_map(|id| self.app_data.tasks.get(id))
+            .collect()
     }
 
     pub fn list_tags(&self) -> Vec<String> {
         self.app_data.tags.keys().cloned().collect()
     }
 
+    /// Every tag and every parent of a nested tag in tree order, with task
+    /// counts rolled up from the tag index over the tag and all tags below it
+    pub fn tag_summaries(&self) -> Vec<TagSummary> {
+        let mut paths = BTreeSet::new();
+        for tag in self.app_data.tags.keys() {
+            let segments = tag.split('/').collect::<Vec<_>>();
+            for depth in 1..=segments.len() {
+                paths.insert(segments[..depth].to_vec());
+            }
+        }
+        paths
+            .into_iter()
+            .map(|segments| {
+                let name = segments.join("/");
+                let ids = self.task_ids_within_tag(&name);
+                let tasks = ids
+                    .iter()
+                    .filter_map(|id| self.app_data.tasks.get(id))
+                    .collect::<Vec<_>>();
+                let workflow = &self.app_data.config.workflow;
+                TagSummary {
+                    open: tasks.iter().filter(|task| workflow.is_open(&task.state)).count(),
+                    done: tasks.iter().filter(|task| task.done).count(),
+                    depth: segments.len() - 1,
+                    info: self.app_data.tag_info.get(&name).cloned().unwrap_or_default(),
+                    name,
+                }
+            })
+            .collect()
+    }
+
+    /// Rename a tag along with every tag nested below it
+    pub fn rename_tag(&mut self, tag: &str, new_name: &str) -> TodoResult<()> {
+        let tag = tag.to_lowercase();
+        let new_name = validate_tag_name(new_name)?;
+        let ids = self.task_ids_within_tag(&tag);
+        if ids.is_empty() {
+            return Err(format!("tag {} not found", tag).into());
+        }
+        // nested tags keep the segments below the renamed one as they were written
+        let depth = tag.split('/').count();
+        let rename = |own: &str| {
+            std::iter::once(new_name)
+                .chain(own.split('/').skip(depth))
+                .collect::<Vec<_>>()
+                .join("/")
+        };
+        let described = self
+            .app_data
+            .tag_info
+            .keys()
+            .filter(|key| is_tag_within(key, &tag))
+            .cloned()
+            .collect::<Vec<_>>();
+        for key in described {
+            let info = self.app_data.tag_info.remove(&key).unwrap();
+            self.app_data.tag_info.insert(rename(&key).to_lowercase(), info);
+        }
+        for id in ids {
+            let tags = self.app_data.tasks[&id]
+                .tags
+                .iter()
+                .flatten()
+                .map(|own| match is_tag_within(own, &tag) {
+                    true => rename(own),
+                    false => own.clone(),
+                })
+                .collect();
+            let update = TaskUpdate {
+                tags: Some(tags),
+                ..Default::default()
+            };
+            self.update_tasks(&[id], &update)?;
+        }
+        self.app_data.save()?;
+        Ok(())
+    }
+
+    /// Replace given tags with `into` on every task carrying any of them
+    pub fn merge_tags(&mut self, tags: &[String], into: &str) -> TodoResult<()> {
+        let into = validate_tag_name(into)?;
+        let tags = tags.iter().map(|tag| tag.to_lowercase()).collect::<Vec<_>>();
+        let key = into.to_lowercase();
+        // the target keeps its own details, otherwise it inherits the first source's
+        let mut info = self.app_data.tag_info.remove(&key);
+        for tag in &tags {
+            let source_info = self.app_data.tag_info.remove(tag);
+            info = info.or(source_info);
+        }
+        if let Some(info) = info {
+            self.app_data.tag_info.insert(key, info);
+        }
+        self.retag(&tags, into)?;
+        self.app_data.save()?;
+        Ok(())
+    }
+
+    /// Remove given tag from every task carrying it
+    pub fn delete_tag(&mut self, tag: &str) -> TodoResult<()> {
+        let tag = tag.to_lowercase();
+        let ids = self.tagged_task_ids(std::slice::from_ref(&tag))?;
+        self.app_data.tag_info.remove(&tag);
+        self.edit_tasks(
+            &ids,
+            TaskUpdate {
+                remove_tags: vec![tag],
+                ..Default::default()
+            },
+        )
+    }
+
+    /// Swap given lowercase tags for `into` on the tasks carrying them, without saving
+    fn retag(&mut self, tags: &[String], into: &str) -> TodoResult<()> {
+        let ids = self.tagged_task_ids(tags)?;
+        self.update_tasks(
+            &ids,
+            &TaskUpdate {
+                remove_tags: tags.to_vec(),
+                add_tags: vec![into.to_string()],
+                ..Default::default()
+            },
+        )
+    }
+
+    pub fn describe_tag(&mut self, tag: &str, description: String) -> TodoResult<()> {
+        let info = self.tag_info_mut(tag)?;
+        info.description = Some(description).filter(|d| !d.is_empty());
+        self.app_data.save()
+    }
+
+    pub fn color_tag(&mut self, tag: &str, color: &str) -> TodoResult<()> {
+        let color = color.to_lowercase();
+        if !color.is_empty() && color.parse::<Color>().is_err() {
+            return Err(format!("Unknown color {}", color).into());
+        }
+        let info = self.tag_info_mut(tag)?;
+        info.color = Some(color).filter(|c| !c.is_empty());
+        self.app_data.save()
+    }
+
+    fn tag_info_mut(&mut self, tag: &str) -> TodoResult<&mut TagInfo> {
+        let tag = tag.to_lowercase();
+        if self.task_ids_within_tag(&tag).is_empty() {
+            return Err(format!("tag {} not found", tag).into());
+        }
+        Ok(self.app_data.tag_info.entry(tag).or_default())
+    }
+
+    /// Ids of tasks carrying given tag or any tag nested below it
+    fn task_ids_within_tag(&self, tag: &str) -> Vec<usize> {
+        let mut ids = self
+            .app_data
+            .tags
+            .iter()
+            .filter(|(key, _)| is_tag_within(key, tag))
+            .flat_map(|(_, ids)| ids.iter().copied())
+            .collect::<Vec<_>>();
+        ids.sort();
+        ids.dedup();
+        ids
+    }
+
+    /// Ids of tasks carrying any of given tags or a tag nested below one of them
+    fn task_ids_within_tags(&self, tags: &[String]) -> Vec<usize> {
+        let mut ids = tags
+            .iter()
+            .flat_map(|tag| self.task_ids_within_tag(tag))
+            .collect::<Vec<_>>();
+        ids.sort();
+        ids.dedup();
+        ids
+    }
+
+    /// Ids of tasks carrying any of given lowercase tags, all of which must exist
+    fn tagged_task_ids(&self, tags: &[String]) -> TodoResult<Vec<usize>> {
+        let mut ids = vec![];
+        for tag in tags {
+            match self.app_data.tags.get(tag) {
+                Some(tag_tasks) => ids.extend(tag_tasks),
+                None => return Err(format!("tag {} not found", tag).into()),
+            }
+        }
+        ids.sort();
+        ids.dedup();
+        Ok(ids)
+    }
+
+    #[allow(clippy::too_many_arguments)]
     pub fn filter_tasks(
         &self,
-        priority: bool,
+        priority: Option<PriorityFilter>,
         due_date: Option<String>,
-        tags: Option<Vec<String>>,
+        tag_filter: TagFilter,
+        state: Option<String>,
         done: bool,
+        completed_since: Option<String>,
         show_all: bool,
     ) -> TodoResult<Vec<&Task>> {
+        let workflow = &self.app_data.config.workflow;
+        if let Some(state) = &state {
+            workflow.check_state(state)?;
+        }
         let due_date = match due_date {
             Some(due_date) => Some(
                 NaiveDate::parse_from_str(&due_date, &self.app_data.config.date_format)
@@ -148,6 +735,24 @@ impl TaskManager {
             ),
             None => None,
         };
+        let completed_since = match completed_since {
+            Some(since) => Some(parse_since(
+                &since,
+                &self.app_data.config.date_format,
+                chrono::Local::now().date_naive(),
+            )?),
+            None => None,
+        };
+        let required_ids = tag_filter
+            .all
+            .iter()
+            .map(|tag| self.task_ids_within_tag(tag))
+            .collect::<Vec<_>>();
+        let any_ids = match tag_filter.any.is_empty() {
+            true => None,
+            false => Some(self.task_ids_within_tags(&tag_filter.any)),
+        };
+        let excluded_ids = self.task_ids_within_tags(&tag_filter.exclude);
         let filtered_tasks = self.app_data
             .tasks
             .values()
@@ -155,12 +760,28 @@ impl TaskManager {
                 if show_all {
                     return true
                 }
-                // filter by done
-                if (done && !task.done) || (!done && task.done){
+                // filter by state, by default only open tasks are listed
+                let listed = match &state {
+                    Some(state) => task.state == *state,
+                    None if done => task.done,
+                    None => workflow.is_open(&task.state),
+                };
+                if !listed {
                     return false;
                 }
+                // filter by completion date, tasks without one never match
+                if let Some(since) = completed_since {
+                    let completed_on = task
+                        .completed_at
+                        .as_deref()
+                        .and_then(parse_timestamp)
+                        .map(|completed_at| completed_at.date());
+                    if completed_on.is_none_or(|date| date < since) {
+                        return false;
+                    }
+                }
                 // filter by priority
-                if priority && !task.priority {
+                if priority.is_some_and(|priority| !priority.matches(task.priority)) {
                     return false;
                 }
                 // filter by due date
@@ -172,24 +793,18 @@ impl TaskManager {
                         return false;
                     }
                 }
-                if let Some(given_tags) = &tags {
-                    if self.app_data.tags.is_empty() {
-                        return false;
-                    }
-                    let available_tags = self.app_data.tags.keys().collect::<Vec<&String>>();
-                    // if any of the given tags is not available, return false
-                    for tag in given_tags {
-                        if !available_tags.contains(&tag) {
-                            return false;
-                        }
-                        let entry = self.app_data.tags.get(tag).unwrap();
-                        if entry.is_empty() || !entry.contains(&task.id) {
-                            return false;
-                        }
-                    }
+                // filter by tags, each given tag matches the tags nested below it too
+                if !required_ids.iter().all(|ids| ids.contains(&task.id)) {
+                    return false;
+                }
+                if any_ids.as_ref().is_some_and(|ids| !ids.contains(&task.id)) {
+                    return false;
+                }
+                if excluded_ids.contains(&task.id) {
+                    return false;
                 }
-                return true;
-            }).map(|task| task).collect::<Vec<&Task>>();
+                true
+            }).collect::<Vec<&Task>>();
         Ok(filtered_tasks)
     }
 }
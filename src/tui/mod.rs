@@ -0,0 +1,624 @@
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind};
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style, Stylize};
+use ratatui::text::Line;
+use ratatui::widgets::{
+    Block, Borders, Clear, List, ListItem, ListState, Paragraph, Row, Table, TableState,
+};
+use ratatui::{DefaultTerminal, Frame};
+
+use crate::{
+    sort_tasks, Priority, PriorityFilter, Task, TaskManager, TaskUpdate, TodoResult, Workflow,
+};
+
+/// Run the full-screen interface until the user quits
+pub fn run(task_manager: TaskManager) -> TodoResult<()> {
+    let mut terminal = ratatui::init();
+    let result = Tui::new(task_manager).run(&mut terminal);
+    ratatui::restore();
+    result
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Pane {
+    Tasks,
+    Tags,
+    Priority,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Status {
+    Open,
+    Done,
+    All,
+}
+
+impl Status {
+    fn next(self) -> Self {
+        match self {
+            Status::Open => Status::Done,
+            Status::Done => Status::All,
+            Status::All => Status::Open,
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            Status::Open => "Open",
+            Status::Done => "Done",
+            Status::All => "All",
+        }
+    }
+}
+
+struct Filter {
+    search: String,
+    tag: Option<String>,
+    priority: Option<PriorityFilter>,
+    status: Status,
+}
+
+/// Entries of the priority pane with the lowest level each of them lists
+const PRIORITY_FILTERS: [(&str, Option<Priority>); 5] = [
+    ("All", None),
+    ("Critical", Some(Priority::Critical)),
+    ("High and above", Some(Priority::High)),
+    ("Medium and above", Some(Priority::Medium)),
+    ("Low and above", Some(Priority::Low)),
+];
+
+const FORM_FIELDS: [&str; 4] = ["Name", "Due date", "Tags", "Priority"];
+const FORM_PRIORITY: usize = 3;
+
+struct TaskForm {
+    id: Option<usize>,
+    name: String,
+    due_date: String,
+    tags: String,
+    priority: Priority,
+    focus: usize,
+}
+
+impl TaskForm {
+    fn field_mut(&mut self) -> Option<&mut String> {
+        match self.focus {
+            0 => Some(&mut self.name),
+            1 => Some(&mut self.due_date),
+            2 => Some(&mut self.tags),
+            _ => None,
+        }
+    }
+}
+
+enum Mode {
+    Normal,
+    Search,
+    Form(TaskForm),
+    ConfirmRemove(usize),
+}
+
+struct Tui {
+    task_manager: TaskManager,
+    filter: Filter,
+    mode: Mode,
+    focus: Pane,
+    tasks_state: TableState,
+    tags_state: ListState,
+    priority_state: ListState,
+    message: Option<String>,
+    quit: bool,
+}
+
+impl Tui {
+    fn new(task_manager: TaskManager) -> Self {
+        Self {
+            task_manager,
+            filter: Filter {
+                search: String::new(),
+                tag: None,
+                priority: None,
+                status: Status::Open,
+            },
+            mode: Mode::Normal,
+            focus: Pane::Tasks,
+            tasks_state: TableState::default().with_selected(Some(0)),
+            tags_state: ListState::default().with_selected(Some(0)),
+            priority_state: ListState::default().with_selected(Some(0)),
+            message: None,
+            quit: false,
+        }
+    }
+
+    fn run(&mut self, terminal: &mut DefaultTerminal) -> TodoResult<()> {
+        while !self.quit {
+            terminal.draw(|frame| self.draw(frame))?;
+            if let Event::Key(key) = event::read()? {
+                if key.kind == KeyEventKind::Press {
+                    self.handle_key(key);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn tags(&self) -> Vec<String> {
+        self.task_manager
+            .tag_summaries()
+            .into_iter()
+            .map(|tag| tag.name)
+            .collect()
+    }
+
+    fn visible_ids(&self) -> Vec<usize> {
+        visible_tasks(&self.task_manager, &self.filter)
+            .iter()
+            .map(|task| task.id)
+            .collect()
+    }
+
+    fn selected_id(&self) -> Option<usize> {
+        let ids = self.visible_ids();
+        self.tasks_state.selected().and_then(|i| ids.get(i).copied())
+    }
+
+    fn handle_key(&mut self, key: KeyEvent) {
+        match std::mem::replace(&mut self.mode, Mode::Normal) {
+            Mode::Normal => self.handle_normal_key(key),
+            Mode::Search => self.handle_search_key(key),
+            Mode::Form(form) => self.handle_form_key(form, key),
+            Mode::ConfirmRemove(id) => {
+                if let KeyCode::Char('y') | KeyCode::Char('Y') = key.code {
+                    self.apply(|tm| tm.remove_task(id), format!("Removed task {}", id));
+                }
+            }
+        }
+    }
+
+    fn handle_normal_key(&mut self, key: KeyEvent) {
+        self.message = None;
+        match key.code {
+            KeyCode::Char('q') => self.quit = true,
+            KeyCode::Esc => self.filter.search.clear(),
+            KeyCode::Tab => {
+                self.focus = match self.focus {
+                    Pane::Tasks => Pane::Tags,
+                    Pane::Tags => Pane::Priority,
+                    Pane::Priority => Pane::Tasks,
+                }
+            }
+            KeyCode::Up | KeyCode::Char('k') => self.move_selection(-1),
+            KeyCode::Down | KeyCode::Char('j') => self.move_selection(1),
+            KeyCode::Char('/') => self.mode = Mode::Search,
+            KeyCode::Char('s') => {
+                self.filter.status = self.filter.status.next();
+                self.tasks_state.select(Some(0));
+            }
+            KeyCode::Char('a') => {
+                self.mode = Mode::Form(TaskForm {
+                    id: None,
+                    name: String::new(),
+                    due_date: String::new(),
+                    tags: self.filter.tag.clone().unwrap_or_default(),
+                    priority: Priority::None,
+                    focus: 0,
+                })
+            }
+            KeyCode::Char('e') | KeyCode::Enter => self.open_edit_form(),
+            KeyCode::Char('d') | KeyCode::Char(' ') => self.toggle_done(),
+            KeyCode::Char('p') => self.cycle_priority(),
+            KeyCode::Char('x') | KeyCode::Delete => {
+                if let Some(id) = self.selected_id() {
+                    self.mode = Mode::ConfirmRemove(id);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn handle_search_key(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Esc => self.filter.search.clear(),
+            KeyCode::Enter => {}
+            KeyCode::Backspace => {
+                self.filter.search.pop();
+                self.mode = Mode::Search;
+            }
+            KeyCode::Char(c) => {
+                self.filter.search.push(c);
+                self.mode = Mode::Search;
+            }
+            _ => self.mode = Mode::Search,
+        }
+        self.tasks_state.select(Some(0));
+    }
+
+    fn handle_form_key(&mut self, mut form: TaskForm, key: KeyEvent) {
+        match key.code {
+            KeyCode::Esc => return,
+            KeyCode::Enter => {
+                if let Err(e) = self.submit_form(&form) {
+                    self.message = Some(e.to_string());
+                    self.mode = Mode::Form(form);
+                }
+                return;
+            }
+            KeyCode::Tab | KeyCode::Down => form.focus = (form.focus + 1) % FORM_FIELDS.len(),
+            KeyCode::BackTab | KeyCode::Up => {
+                form.focus = (form.focus + FORM_FIELDS.len() - 1) % FORM_FIELDS.len()
+            }
+            KeyCode::Char(' ') if form.focus == FORM_PRIORITY => form.priority = form.priority.next(),
+            KeyCode::Backspace => {
+                if let Some(field) = form.field_mut() {
+                    field.pop();
+                }
+            }
+            KeyCode::Char(c) => {
+                if let Some(field) = form.field_mut() {
+                    field.push(c);
+                }
+            }
+            _ => {}
+        }
+        self.mode = Mode::Form(form);
+    }
+
+    fn submit_form(&mut self, form: &TaskForm) -> TodoResult<()> {
+        let name = form.name.trim().to_string();
+        if name.is_empty() {
+            return Err("Task name can't be empty".into());
+        }
+        let due_date = Some(form.due_date.trim().to_string()).filter(|d| !d.is_empty());
+        let tags = form
+            .tags
+            .split(',')
+            .map(|tag| tag.trim().to_string())
+            .filter(|tag| !tag.is_empty())
+            .collect::<Vec<_>>();
+        let tags = Some(tags).filter(|tags| !tags.is_empty());
+        match form.id {
+            Some(id) => {
+                self.task_manager.edit_task(
+                    id,
+                    TaskUpdate {
+                        name: Some(name),
+                        priority: Some(form.priority),
+                        due_date: Some(due_date.unwrap_or_default()),
+                        tags: Some(tags.unwrap_or_default()),
+                        ..Default::default()
+                    },
+                )?;
+                self.message = Some(format!("Updated task {}", id));
+            }
+            None => {
+                let id = self
+                    .task_manager
+                    .add_task(name, form.priority, due_date, tags)?;
+                self.message = Some(format!("Added task {}", id));
+            }
+        }
+        Ok(())
+    }
+
+    fn open_edit_form(&mut self) {
+        let Some(id) = self.selected_id() else {
+            return;
+        };
+        let task = &self.task_manager.app_data.tasks[&id];
+        let date_format = &self.task_manager.app_data.config.date_format;
+        self.mode = Mode::Form(TaskForm {
+            id: Some(id),
+            name: task.name.clone(),
+            due_date: task
+                .get_parsed_due_date()
+                .map(|date| date.format(date_format).to_string())
+                .unwrap_or_default(),
+            tags: task.tags.as_ref().map(|tags| tags.join(", ")).unwrap_or_default(),
+            priority: task.priority,
+            focus: 0,
+        });
+    }
+
+    fn toggle_done(&mut self) {
+        let Some(id) = self.selected_id() else {
+            return;
+        };
+        let done = !self.task_manager.app_data.tasks[&id].done;
+        let message = match done {
+            true => format!("Marked task {} as done", id),
+            false => format!("Marked task {} as not done", id),
+        };
+        self.apply(
+            |tm| {
+                tm.edit_task(
+                    id,
+                    TaskUpdate {
+                        done: Some(done),
+                        ..Default::default()
+                    },
+                )
+            },
+            message,
+        );
+    }
+
+    fn cycle_priority(&mut self) {
+        let Some(id) = self.selected_id() else {
+            return;
+        };
+        let priority = self.task_manager.app_data.tasks[&id].priority.next();
+        self.apply(
+            |tm| {
+                tm.edit_task(
+                    id,
+                    TaskUpdate {
+                        priority: Some(priority),
+                        ..Default::default()
+                    },
+                )
+            },
+            format!("Updated task {}", id),
+        );
+    }
+
+    fn apply<F>(&mut self, action: F, message: String)
+    where
+        F: FnOnce(&mut TaskManager) -> TodoResult<()>,
+    {
+        self.message = Some(match action(&mut self.task_manager) {
+            Ok(()) => message,
+            Err(e) => e.to_string(),
+        });
+        self.clamp_selection();
+    }
+
+    fn move_selection(&mut self, delta: isize) {
+        match self.focus {
+            Pane::Tasks => {
+                let len = self.visible_ids().len();
+                step(self.tasks_state.selected_mut(), delta, len);
+            }
+            Pane::Tags => {
+                // first entry of the pane means "all tags"
+                let tags = self.tags();
+                step(self.tags_state.selected_mut(), delta, tags.len() + 1);
+                self.filter.tag = self
+                    .tags_state
+                    .selected()
+                    .and_then(|i| i.checked_sub(1))
+                    .and_then(|i| tags.get(i).cloned());
+                self.tasks_state.select(Some(0));
+            }
+            Pane::Priority => {
+                step(self.priority_state.selected_mut(), delta, PRIORITY_FILTERS.len());
+                self.filter.priority = self
+                    .priority_state
+                    .selected()
+                    .and_then(|i| PRIORITY_FILTERS[i].1)
+                    .map(PriorityFilter::at_least);
+                self.tasks_state.select(Some(0));
+            }
+        }
+    }
+
+    fn clamp_selection(&mut self) {
+        let len = self.visible_ids().len();
+        if let Some(selected) = self.tasks_state.selected() {
+            if selected >= len {
+                self.tasks_state.select(Some(len.saturating_sub(1)));
+            }
+        }
+        // a removed tag leaves the tag filter pointing at nothing
+        if let Some(tag) = &self.filter.tag {
+            if !self.tags().contains(tag) {
+                self.filter.tag = None;
+                self.tags_state.select(Some(0));
+            }
+        }
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [main, status_bar] =
+            Layout::vertical([Constraint::Min(0), Constraint::Length(1)]).areas(frame.area());
+        let [sidebar, content] =
+            Layout::horizontal([Constraint::Length(24), Constraint::Min(0)]).areas(main);
+        let [tags_area, priority_area] =
+            Layout::vertical([Constraint::Min(0), Constraint::Length(PRIORITY_FILTERS.len() as u16 + 2)])
+                .areas(sidebar);
+        let [search_area, tasks_area] =
+            Layout::vertical([Constraint::Length(3), Constraint::Min(0)]).areas(content);
+
+        let tags = std::iter::once("All".to_string())
+            .chain(self.task_manager.tag_summaries().into_iter().map(|tag| {
+                let label = tag.name.rsplit('/').next().unwrap_or_default();
+                format!("{}{} ({})", "  ".repeat(tag.depth), label, tag.open)
+            }))
+            .map(ListItem::new)
+            .collect::<Vec<_>>();
+        frame.render_stateful_widget(
+            List::new(tags)
+                .block(pane_block("Tags", self.focus == Pane::Tags))
+                .highlight_style(highlight_style()),
+            tags_area,
+            &mut self.tags_state,
+        );
+        frame.render_stateful_widget(
+            List::new(PRIORITY_FILTERS.map(|(label, _)| label))
+                .block(pane_block("Priority", self.focus == Pane::Priority))
+                .highlight_style(highlight_style()),
+            priority_area,
+            &mut self.priority_state,
+        );
+
+        let searching = matches!(self.mode, Mode::Search);
+        frame.render_widget(
+            Paragraph::new(self.filter.search.as_str())
+                .block(pane_block("Search (/)", searching)),
+            search_area,
+        );
+        if searching {
+            frame.set_cursor_position((
+                search_area.x + 1 + self.filter.search.chars().count() as u16,
+                search_area.y + 1,
+            ));
+        }
+
+        let tasks = visible_tasks(&self.task_manager, &self.filter);
+        let workflow = &self.task_manager.app_data.config.workflow;
+        let rows = tasks
+            .iter()
+            .map(|task| task_row(task, workflow))
+            .collect::<Vec<_>>();
+        let table = Table::new(
+            rows,
+            [
+                Constraint::Length(5),
+                Constraint::Min(20),
+                Constraint::Length(8),
+                Constraint::Length(12),
+                Constraint::Length(20),
+                Constraint::Length(12),
+            ],
+        )
+        .header(
+            Row::new(["ID", "Title", "Priority", "Due Date", "Tags", "State"])
+                .style(Style::new().bold().light_blue()),
+        )
+        .block(pane_block(
+            &format!("Tasks ({}, {})", self.filter.status.label(), tasks.len()),
+            self.focus == Pane::Tasks,
+        ))
+        .row_highlight_style(highlight_style());
+        frame.render_stateful_widget(table, tasks_area, &mut self.tasks_state);
+
+        let status = match (&self.mode, &self.message) {
+            (Mode::ConfirmRemove(id), _) => format!("Remove task {}? (y/n)", id),
+            (_, Some(message)) => message.clone(),
+            (Mode::Form(_), None) => {
+                "Tab: next field  Space: change priority  Enter: save  Esc: cancel".to_string()
+            }
+            (Mode::Search, None) => "Enter: keep search  Esc: clear search".to_string(),
+            (Mode::Normal, None) => "q: quit  Tab: switch pane  j/k: move  /: search  a: add  e: edit  d: done  p: priority  x: remove  s: status"
+                .to_string(),
+        };
+        frame.render_widget(Paragraph::new(status).dim(), status_bar);
+
+        if let Mode::Form(form) = &self.mode {
+            draw_form(frame, form, main);
+        }
+    }
+}
+
+fn visible_tasks<'a>(task_manager: &'a TaskManager, filter: &Filter) -> Vec<&'a Task> {
+    let search = filter.search.to_lowercase();
+    let tasks = task_manager
+        .app_data
+        .tasks
+        .values()
+        .filter(|task| match filter.status {
+            Status::Open => task_manager.app_data.config.workflow.is_open(&task.state),
+            Status::Done => task.done,
+            Status::All => true,
+        })
+        .filter(|task| filter.priority.is_none_or(|priority| priority.matches(task.priority)))
+        .filter(|task| filter.tag.as_ref().is_none_or(|tag| task.has_tag(tag)))
+        .filter(|task| search.is_empty() || task.matches(&search))
+        .collect::<Vec<_>>();
+    let mut tasks = sort_tasks(tasks);
+    tasks.reverse();
+    tasks
+}
+
+fn task_row(task: &Task, workflow: &Workflow) -> Row<'static> {
+    let style = if task.done {
+        Style::new().green()
+    } else if task.state == workflow.cancelled {
+        Style::new().dark_gray()
+    } else if task.is_due_today() {
+        Style::new().red()
+    } else if task.priority == Priority::Critical {
+        Style::new().magenta()
+    } else if task.priority == Priority::High {
+        Style::new().yellow()
+    } else {
+        Style::new()
+    };
+    Row::new([
+        task.id.to_string(),
+        task.name.clone(),
+        match task.priority {
+            Priority::None => "-".to_string(),
+            priority => priority.to_string(),
+        },
+        task.due_date.clone().unwrap_or_else(|| "-".to_string()),
+        task.tags
+            .as_ref()
+            .map(|tags| tags.join(", "))
+            .unwrap_or_else(|| "-".to_string()),
+        task.state.clone(),
+    ])
+    .style(style)
+}
+
+fn draw_form(frame: &mut Frame, form: &TaskForm, area: Rect) {
+    let width = area.width.min(60);
+    let height = FORM_FIELDS.len() as u16 + 2;
+    let area = Rect::new(
+        area.x + (area.width - width) / 2,
+        area.y + area.height.saturating_sub(height) / 2,
+        width,
+        height.min(area.height),
+    );
+    let values = [
+        form.name.clone(),
+        form.due_date.clone(),
+        form.tags.clone(),
+        form.priority.to_string(),
+    ];
+    let lines = FORM_FIELDS
+        .iter()
+        .zip(values)
+        .enumerate()
+        .map(|(i, (label, value))| {
+            let line = Line::from(format!("{:>9}: {}", label, value));
+            match i == form.focus {
+                true => line.style(Style::new().add_modifier(Modifier::BOLD).fg(Color::Cyan)),
+                false => line,
+            }
+        })
+        .collect::<Vec<_>>();
+    let title = match form.id {
+        Some(id) => format!("Edit task {}", id),
+        None => "Add task".to_string(),
+    };
+    frame.render_widget(Clear, area);
+    frame.render_widget(Paragraph::new(lines).block(pane_block(&title, true)), area);
+    if let Some(value) = [&form.name, &form.due_date, &form.tags].get(form.focus) {
+        frame.set_cursor_position((
+            area.x + 12 + value.chars().count() as u16,
+            area.y + 1 + form.focus as u16,
+        ));
+    }
+}
+
+fn pane_block(title: &str, focused: bool) -> Block<'static> {
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .title(title.to_string());
+    match focused {
+        true => block.border_style(Style::new().cyan()),
+        false => block,
+    }
+}
+
+fn highlight_style() -> Style {
+    Style::new().add_modifier(Modifier::REVERSED)
+}
+
+fn step(selected: &mut Option<usize>, delta: isize, len: usize) {
+    if len == 0 {
+        *selected = None;
+        return;
+    }
+    let current = selected.unwrap_or(0) as isize;
+    *selected = Some((current + delta).clamp(0, len as isize - 1) as usize);
+}